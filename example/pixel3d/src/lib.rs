@@ -1,146 +1,41 @@
 #![allow(warnings)]
 
 use log::warn;
-use winit::event::WindowEvent;
-use winit::dpi::PhysicalSize;
-use wasm_bindgen::prelude::wasm_bindgen;
-use wasm_bindgen_futures::spawn_local;
 use wgpu::*;
 
 use pixel::*;
 
-#[wasm_bindgen]
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
 pub fn main() {
-    spawn_local(run());
-}
-
-pub async fn run() {
-
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
     console_log::init_with_level(log::Level::Warn);
-
-    let main_loop = winit::event_loop::EventLoop::new().unwrap();
-    let mut window = winit::window::WindowBuilder::new().build(&main_loop).unwrap();
-    window.request_inner_size(PhysicalSize::new(640, 640));
-
-    let ctx = WebGPUContextBuilder::new(&window)
-        .await
-        .build()
-        .await;
-
-    let world = GameWorld::new(ctx, &window).await;
-
-    let mut player = world.create_entity();
-    player.add_shader_mesh_uniform();
-
-
-    let angle_x = 30.0_f32.to_radians(); // Ровно 30 градусов в радианах
-    let angle_y = 30.0_f32.to_radians(); // Ровно 30 градусов в радианах
-
-// Матрица поворота по оси X
-let rotation_x = [
-    [1.0, 0.0, 0.0, 0.0],
-    [0.0, angle_x.cos(), -angle_x.sin(), 0.0],
-    [0.0, angle_x.sin(), angle_x.cos(), 0.0],
-    [0.0, 0.0, 0.0, 1.0],
-];
-
-// Матрица поворота по оси Y
-let rotation_y = [
-    [angle_y.cos(), 0.0, angle_y.sin(), 0.0],
-    [0.0, 1.0, 0.0, 0.0],
-    [-angle_y.sin(), 0.0, angle_y.cos(), 0.0],
-    [0.0, 0.0, 0.0, 1.0],
-];
-
-let mut final_rotation = [[0.0; 4]; 4];
-
-for i in 0..4 {
-    for j in 0..4 {
-        final_rotation[i][j] =
-            rotation_y[i][0] * rotation_x[0][j] +
-            rotation_y[i][1] * rotation_x[1][j] +
-            rotation_y[i][2] * rotation_x[2][j] +
-            rotation_y[i][3] * rotation_x[3][j];
-    }
+    wasm_bindgen_futures::spawn_local(run());
 }
 
-let c = [
-    [0.2, 0.0, 0.0, 0.0],
-    [0.0, 0.2, 0.0, 0.0],
-    [0.0, 0.0, 0.2, 0.0],
-    [0.0, 0.0, 0.0, 1.0],
-];
-
-let mut final_rotation2 = [[0.0; 4]; 4];
-
-for i in 0..4 {
-    for j in 0..4 {
-        final_rotation2[i][j] =
-            final_rotation[i][0] * c[0][j] +
-            final_rotation[i][1] * c[1][j] +
-            final_rotation[i][2] * c[2][j] +
-            final_rotation[i][3] * c[3][j];
-    }
+pub async fn run() {
+    App::new()
+        .with_title("pixel3d")
+        .with_inner_size(640, 640)
+        .on_startup(setup)
+        .on_render(|world| world.render())
+        .run()
+        .await;
 }
 
-// Создаём камеру с итоговой матрицей
-let mut _max = Camera {
-    matrix: final_rotation2,
-};
+fn setup(world: &mut GameWorld) {
 
+    let mut camera = world.create_entity();
+    camera.add_component(Camera::perspective(45f32.to_radians(), 0.1, 100.0));
+    camera.add_component(Transform::from_xyz(0.0, 0.0, 3.0).looking_at(Vec3::ZERO, Vec3::Y));
+    camera.add_component(OrbitController::new(Vec3::ZERO, 3.0));
 
-let angle = 3f32;
-let rad = (3.14 * angle)/180.0;
-let mut vertex = vec![];
+    let mut player = world.create_entity();
 
-for i in 0..360 {
-    vertex.push(Vertex3D{pos: [
-        (rad * i as f32).cos(),
-        (rad * i as f32).sin(),
-        0.0
-    ],  color: [(rad * i as f32).cos(), (rad * i as f32).sin(), (rad * i as f32).cos()]});
+    let (vertex, index) = shapes::cube(1.0, 1).into_mesh_normal_colors();
 
-    vertex.push(Vertex3D{pos: [0.0, 0.0, 0.0], color: [(rad * i as f32).sin(), ((rad * i as f32).cos()), (rad * i as f32).sin()]});
-    vertex.push(Vertex3D{pos: [(rad * (i+1) as f32).cos(), (rad * (i+1) as f32).sin(), 0.0], color: [(rad * i as f32).cos(), (rad * i as f32).sin(), (rad * i as f32).cos()]});
+    player.add_component(Transform::from_rotation(Quat::from_euler(EulerRot::YXZ, 30f32.to_radians(), 30f32.to_radians(), 0.0)));
+    player.add_mesh(vertex, index);
+    player.add_shader_mesh();
+    player.add_mesh_pipeline(PrimitiveTopology::TriangleList);
 }
-
-_max.matrix =c;
-
-player.add_mesh(vertex, None);
-player.add_shader_mesh_uniform();
-player.add_uniform(ShaderStages::VERTEX, _max);
-player.add_mesh_uniform_pipeline(PrimitiveTopology::TriangleStrip);
-
-    main_loop.run(move |event, event_loop_window_target| {
-
-        match event {
-
-            winit::event::Event::AboutToWait => {
-
-            }
-
-            winit::event::Event::WindowEvent { window_id, event } => {
-
-                match event {
-
-                    WindowEvent::Destroyed => {
-
-                    }
-
-                    WindowEvent::RedrawRequested => {
-                        world.draw_mesh_uniform(vec![&player]);
-                    }
-
-                    WindowEvent::Resized(size) => {
-                        world.resize(size);
-                    }
-
-                    _ => ()
-                }
-            }
-            _ => ()
-        }
-    });
-
-}
\ No newline at end of file
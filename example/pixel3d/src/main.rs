@@ -0,0 +1,8 @@
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    pixel::block_on(pixel3d::run());
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use winit::keyboard::KeyCode;
+
+use crate::{Input, MouseButton, TouchPoint, PIXELS_PER_LINE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TouchGesture {
+    /// Any finger on the screen
+    Any,
+    /// A finger inside a rectangle of the screen in `0.0..=1.0` coordinates
+    /// from the top-left corner, for on-screen buttons
+    Region { min: (f32, f32), max: (f32, f32) },
+    /// A finger lifted after moving at least a tenth of the screen,
+    /// active for a single frame
+    Swipe(SwipeDirection),
+}
+
+/// One physical input that can trigger an action.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Touch(TouchGesture),
+}
+
+/// One physical input that contributes to an axis.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    /// `-1.0` while `negative` is held, `1.0` while `positive` is held
+    Digital { negative: Binding, positive: Binding },
+    /// Raw horizontal mouse motion this frame times the scale
+    MouseX(f32),
+    /// Raw vertical mouse motion this frame times the scale
+    MouseY(f32),
+    /// Vertical wheel lines this frame times the scale, touchpad pixels
+    /// counted as lines
+    Scroll(f32),
+    /// Horizontal drag of the first finger this frame times the scale
+    TouchDragX(f32),
+    /// Vertical drag of the first finger this frame times the scale
+    TouchDragY(f32),
+}
+
+const SWIPE_DISTANCE: f32 = 0.1;
+
+/// Named actions ("jump") and axes ("move_x") bound to keys, mouse buttons
+/// and touch gestures, so gameplay code never hardcodes a `KeyCode`.
+///
+/// Bindings are serialized as JSON, the per-frame state is not.
+///
+/// ```ignore
+/// let actions = world.resource_mut::<ActionMap>();
+/// actions.bind("jump", Binding::Key(KeyCode::Space));
+/// actions.bind("jump", Binding::Touch(TouchGesture::Swipe(SwipeDirection::Up)));
+/// actions.bind_axis("move_x", AxisBinding::Digital {
+///     negative: Binding::Key(KeyCode::KeyA),
+///     positive: Binding::Key(KeyCode::KeyD),
+/// });
+///
+/// if world.resource::<ActionMap>().just_pressed("jump") { /* ... */ }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionMap {
+    actions:    BTreeMap<String, Vec<Binding>>,
+    axes:       BTreeMap<String, Vec<AxisBinding>>,
+    #[serde(skip)]
+    pressed:    HashSet<String>,
+    #[serde(skip)]
+    previous:   HashSet<String>,
+    #[serde(skip)]
+    axis_value: BTreeMap<String, f32>,
+}
+
+impl ActionMap {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding to an action, creating the action if needed
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Replaces `old` with `new` in place, keeping the binding order
+    pub fn rebind(&mut self, action: &str, old: Binding, new: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            match bindings.iter().position(|b| *b == old) {
+                Some(i) => bindings[i] = new,
+                None => bindings.push(new),
+            }
+        }
+    }
+
+    pub fn clear_bindings(&mut self, action: &str) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.clear();
+        }
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], |b| b.as_slice())
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        if let Some(bindings) = self.axes.get_mut(axis) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], |b| b.as_slice())
+    }
+
+    /// The first key or mouse button pressed this frame, for "press a key
+    /// to rebind" menus.
+    pub fn capture(input: &Input) -> Option<Binding> {
+        if let Some(key) = input.keys.get_just_pressed().next() {
+            return Some(Binding::Key(*key));
+        }
+        input.mouse_buttons.get_just_pressed().next().map(|b| Binding::Mouse(*b))
+    }
+
+    /// Recomputes action and axis state, called once per frame after the
+    /// input was fed.
+    pub fn update(&mut self, input: &Input) {
+        std::mem::swap(&mut self.previous, &mut self.pressed);
+        self.pressed.clear();
+
+        for (name, bindings) in &self.actions {
+            if bindings.iter().any(|b| binding_active(b, input)) {
+                self.pressed.insert(name.clone());
+            }
+        }
+
+        self.axis_value.clear();
+        for (name, bindings) in &self.axes {
+            let mut digital = 0.0;
+            let mut analog = 0.0;
+
+            for binding in bindings {
+                match binding {
+                    AxisBinding::Digital { .. } => digital += axis_value(binding, input),
+                    _ => analog += axis_value(binding, input),
+                }
+            }
+
+            self.axis_value.insert(name.clone(), digital.clamp(-1.0, 1.0) + analog);
+        }
+    }
+
+    pub fn pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action) && !self.previous.contains(action)
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        !self.pressed.contains(action) && self.previous.contains(action)
+    }
+
+    /// Sum of all bindings of the axis, digital bindings are clamped to `-1.0..=1.0`
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.axis_value.get(axis).copied().unwrap_or(0.0)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Error serialize action map")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+fn binding_active(binding: &Binding, input: &Input) -> bool {
+    match binding {
+        Binding::Key(key) => input.key_pressed(*key),
+        Binding::Mouse(button) => input.mouse_pressed(*button),
+        Binding::Touch(gesture) => gesture_active(gesture, input),
+    }
+}
+
+fn gesture_active(gesture: &TouchGesture, input: &Input) -> bool {
+    match gesture {
+        TouchGesture::Any => input.touches().next().is_some(),
+
+        TouchGesture::Region { min, max } => input.touches().any(|t| {
+            match input.normalize_position(t.position) {
+                Some((x, y)) => x >= min.0 && x <= max.0 && y >= min.1 && y <= max.1,
+                None => false,
+            }
+        }),
+
+        TouchGesture::Swipe(direction) => input.just_ended_touches().any(|t| {
+            let (Some(start), Some(end)) = (input.normalize_position(t.start), input.normalize_position(t.position)) else {
+                return false;
+            };
+
+            let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+            let horizontal = dx.abs() >= dy.abs();
+
+            match direction {
+                SwipeDirection::Left => horizontal && dx <= -SWIPE_DISTANCE,
+                SwipeDirection::Right => horizontal && dx >= SWIPE_DISTANCE,
+                SwipeDirection::Up => !horizontal && dy <= -SWIPE_DISTANCE,
+                SwipeDirection::Down => !horizontal && dy >= SWIPE_DISTANCE,
+            }
+        }),
+    }
+}
+
+fn first_touch(input: &Input) -> Option<&TouchPoint> {
+    input.touches().min_by_key(|t| t.id)
+}
+
+fn axis_value(binding: &AxisBinding, input: &Input) -> f32 {
+    match binding {
+        AxisBinding::Digital { negative, positive } => {
+            let mut value = 0.0;
+            if binding_active(negative, input) { value -= 1.0; }
+            if binding_active(positive, input) { value += 1.0; }
+            value
+        }
+        AxisBinding::MouseX(scale) => input.mouse_motion().0 as f32 * scale,
+        AxisBinding::MouseY(scale) => input.mouse_motion().1 as f32 * scale,
+        AxisBinding::Scroll(scale) => (input.scroll_lines().1 + input.scroll_pixels().1 as f32 / PIXELS_PER_LINE) * scale,
+        AxisBinding::TouchDragX(scale) => first_touch(input).map_or(0.0, |t| t.delta.0 as f32 * scale),
+        AxisBinding::TouchDragY(scale) => first_touch(input).map_or(0.0, |t| t.delta.1 as f32 * scale),
+    }
+}
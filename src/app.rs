@@ -0,0 +1,244 @@
+use winit::dpi::PhysicalSize;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::window::WindowBuilder;
+
+use crate::{ActionMap, CanvasOptions, FixedTimestep, GameWorld, GeometryRing, Input, Stage, System, Time, WebGPUContextBuilder, WindowCloseRequested};
+
+type WorldCallback = Box<dyn FnMut(&mut GameWorld)>;
+type WindowEventCallback = Box<dyn FnMut(&mut GameWorld, &WindowEvent)>;
+
+/// Owns the winit event loop, the window and the [`GameWorld`], and drives
+/// user callbacks once per frame with continuous redraw.
+///
+/// ```ignore
+/// App::new()
+///     .with_title("pixel")
+///     .on_startup(|world| { /* create entities */ })
+///     .on_render(|world| { /* draw */ })
+///     .run()
+///     .await;
+/// ```
+pub struct App {
+    title:          String,
+    inner_size:     PhysicalSize<u32>,
+    canvas:         CanvasOptions,
+    fixed_time:     FixedTimestep,
+    systems:        Vec<(Stage, System)>,
+    startup:        Vec<WorldCallback>,
+    fixed_update:   Vec<WorldCallback>,
+    update:         Vec<WorldCallback>,
+    render:         Vec<WorldCallback>,
+    shutdown:       Vec<WorldCallback>,
+    window_event:   Vec<WindowEventCallback>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+
+    pub fn new() -> Self {
+        Self {
+            title:          "pixel".to_string(),
+            inner_size:     PhysicalSize::new(640, 640),
+            canvas:         CanvasOptions::default(),
+            fixed_time:     FixedTimestep::default(),
+            systems:        vec![],
+            startup:        vec![],
+            fixed_update:   vec![],
+            update:         vec![],
+            render:         vec![],
+            shutdown:       vec![],
+            window_event:   vec![],
+        }
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn with_inner_size(mut self, width: u32, height: u32) -> Self {
+        self.inner_size = PhysicalSize::new(width, height);
+        self
+    }
+
+    pub fn with_canvas(mut self, canvas: CanvasOptions) -> Self {
+        self.canvas = canvas;
+        self
+    }
+
+    /// Step length and max steps per frame of the fixed update callbacks,
+    /// 60 Hz with at most 5 steps by default.
+    pub fn with_fixed_timestep(mut self, fixed_time: FixedTimestep) -> Self {
+        self.fixed_time = fixed_time;
+        self
+    }
+
+    /// Registers a system in the world's schedule. The startup stage runs
+    /// after the startup callbacks, the frame stages after the update callbacks.
+    pub fn add_system(mut self, stage: Stage, system: System) -> Self {
+        self.systems.push((stage, system));
+        self
+    }
+
+    /// Runs once after the world is created, before the first frame.
+    pub fn on_startup(mut self, f: impl FnMut(&mut GameWorld) + 'static) -> Self {
+        self.startup.push(Box::new(f));
+        self
+    }
+
+    /// Runs zero or more times per frame at the fixed timestep, before the
+    /// update callbacks. `world.resource::<FixedTimestep>().delta()` is the step length.
+    pub fn on_fixed_update(mut self, f: impl FnMut(&mut GameWorld) + 'static) -> Self {
+        self.fixed_update.push(Box::new(f));
+        self
+    }
+
+    /// Runs every frame before the render callbacks.
+    pub fn on_update(mut self, f: impl FnMut(&mut GameWorld) + 'static) -> Self {
+        self.update.push(Box::new(f));
+        self
+    }
+
+    /// Runs every frame after the update callbacks.
+    pub fn on_render(mut self, f: impl FnMut(&mut GameWorld) + 'static) -> Self {
+        self.render.push(Box::new(f));
+        self
+    }
+
+    /// Runs once when the event loop exits.
+    pub fn on_shutdown(mut self, f: impl FnMut(&mut GameWorld) + 'static) -> Self {
+        self.shutdown.push(Box::new(f));
+        self
+    }
+
+    /// Receives every window event (input, resize, close, ...) before the
+    /// runner handles it.
+    pub fn on_window_event(mut self, f: impl FnMut(&mut GameWorld, &WindowEvent) + 'static) -> Self {
+        self.window_event.push(Box::new(f));
+        self
+    }
+
+    pub async fn run(mut self) {
+
+        let event_loop = EventLoop::new().expect("Error create event loop");
+
+        let window = self.canvas
+            .window_builder(WindowBuilder::new())
+            .with_title(&self.title)
+            .with_inner_size(self.inner_size)
+            .build(&event_loop)
+            .expect("Error create window");
+
+        let window = &window;
+
+        let ctx = WebGPUContextBuilder::new(window)
+            .await
+            .canvas(self.canvas.clone())
+            .build()
+            .await;
+
+        let mut world = GameWorld::new(ctx, window).await;
+        world.insert_resource(self.fixed_time.clone());
+        world.resource_mut::<Input>().set_window_size(window.inner_size());
+
+        for (stage, system) in self.systems.drain(..) {
+            world.add_system(stage, system);
+        }
+
+        for f in &mut self.startup {
+            f(&mut world);
+        }
+        world.apply_commands();
+
+        world.run_startup();
+
+        event_loop.run(move |event, target| {
+
+            match event {
+
+                Event::WindowEvent { event, .. } => {
+
+                    world.resource_mut::<Input>().process_window_event(&event);
+
+                    for f in &mut self.window_event {
+                        f(&mut world, &event);
+                    }
+                    world.apply_commands();
+
+                    match event {
+
+                        WindowEvent::Resized(size) => {
+                            world.resize(size);
+                        }
+
+                        WindowEvent::CloseRequested => {
+                            world.send_event(WindowCloseRequested);
+                            target.exit();
+                        }
+
+                        WindowEvent::RedrawRequested => {
+                            world.resource_mut::<Time>().update();
+                            world.resource_scope(|world, actions: &mut ActionMap| {
+                                actions.update(world.resource::<Input>());
+                            });
+
+                            let delta = world.resource::<Time>().delta_f64();
+                            let steps = world.resource_mut::<FixedTimestep>().advance(delta);
+                            for _ in 0..steps {
+                                for f in &mut self.fixed_update {
+                                    f(&mut world);
+                                }
+                                world.apply_commands();
+                            }
+
+                            for f in &mut self.update {
+                                f(&mut world);
+                            }
+                            world.apply_commands();
+
+                            world.run_schedule();
+
+                            for f in &mut self.render {
+                                f(&mut world);
+                            }
+                            world.apply_commands();
+
+                            world.resource_mut::<Input>().end_frame();
+                            if let Some(ring) = world.get_resource_mut::<GeometryRing>() {
+                                ring.begin_frame();
+                            }
+                            world.clear_trackers();
+                            world.update_events();
+                        }
+
+                        _ => ()
+                    }
+                }
+
+                Event::DeviceEvent { event, .. } => {
+                    world.resource_mut::<Input>().process_device_event(&event);
+                }
+
+                // Keep frames coming instead of waiting for the OS to ask for one
+                Event::AboutToWait => {
+                    window.request_redraw();
+                }
+
+                Event::LoopExiting => {
+                    for f in &mut self.shutdown {
+                        f(&mut world);
+                    }
+                }
+
+                _ => ()
+            }
+
+        }).expect("Error run event loop");
+    }
+}
@@ -1,7 +1,40 @@
-
-
-
-
-struct ComponentBindGroup {
-
-}
\ No newline at end of file
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, Buffer, BufferUsages, Device, ShaderStages};
+
+/// Layout of a bind group with a single vertex stage uniform at binding 0,
+/// used by the camera and model bind groups of the built-in shaders
+pub(crate) fn uniform_bind_group_layout(device: &Device, label: &str) -> BindGroupLayout {
+    device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &[BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None },
+            count: None,
+        }],
+    })
+}
+
+/// Uniform buffer holding `contents` and its bind group for a layout made
+/// by [`uniform_bind_group_layout`]
+pub(crate) fn uniform_bind_group(device: &Device, layout: &BindGroupLayout, label: &str, contents: &[u8]) -> (Buffer, BindGroup) {
+    let buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label:      Some(label),
+        contents,
+        usage:      BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+
+    (buffer, bind_group)
+}
@@ -0,0 +1,229 @@
+use crate::{Mat4, Vec3, Vec4};
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Aabb {
+    pub min:    Vec3,
+    pub max:    Vec3,
+}
+
+impl Aabb {
+
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box holding every point, an empty box at the origin without points
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else {
+            return Self::default();
+        };
+
+        points.fold(Self::new(first, first), |aabb, p| Self::new(aabb.min.min(p), aabb.max.max(p)))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Smallest box holding both boxes
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Box holding this box after `matrix`, larger than the exact shape when rotated
+    pub fn transformed(&self, matrix: &Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        let extents = self.half_extents();
+
+        let half = Vec3::new(
+            matrix.x_axis.x.abs() * extents.x + matrix.y_axis.x.abs() * extents.y + matrix.z_axis.x.abs() * extents.z,
+            matrix.x_axis.y.abs() * extents.x + matrix.y_axis.y.abs() * extents.y + matrix.z_axis.y.abs() * extents.z,
+            matrix.x_axis.z.abs() * extents.x + matrix.y_axis.z.abs() * extents.y + matrix.z_axis.z.abs() * extents.z,
+        );
+
+        Self::new(center - half, center + half)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Sphere through the corners of `aabb`
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        Self::new(aabb.center(), aabb.half_extents().length())
+    }
+
+    /// Sphere around the center of `aabb` reaching every point in `points`
+    pub fn from_points(aabb: &Aabb, points: impl IntoIterator<Item = Vec3>) -> Self {
+        let center = aabb.center();
+        let radius = points.into_iter().map(|p| p.distance_squared(center)).fold(0.0, f32::max).sqrt();
+        Self::new(center, radius)
+    }
+
+    /// Sphere after `matrix`, scaled by its largest axis scale
+    pub fn transformed(&self, matrix: &Mat4) -> Self {
+        let scale = matrix.x_axis.truncate().length()
+            .max(matrix.y_axis.truncate().length())
+            .max(matrix.z_axis.truncate().length());
+
+        Self::new(matrix.transform_point3(self.center), self.radius * scale)
+    }
+}
+
+/// The six planes of a camera's view volume, pointing inwards, as
+/// `(normal, distance)` with `normal · p + distance >= 0` inside.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+
+    /// Planes of a view-projection with depth in `0..1`
+    pub fn from_view_proj(view_proj: &Mat4) -> Self {
+        let (r0, r1, r2, r3) = (view_proj.row(0), view_proj.row(1), view_proj.row(2), view_proj.row(3));
+
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|plane| {
+            let length = plane.truncate().length();
+            if length > 0.0 { plane / length } else { plane }
+        });
+
+        Self { planes }
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(sphere.center) + plane.w >= -sphere.radius)
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            // Corner furthest along the normal
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+/// Keeps an entity drawn even outside the camera frustum, e.g. when its
+/// shader moves the vertices
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoFrustumCulling;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Quat;
+
+    fn cube(center: Vec3, half: f32) -> Aabb {
+        Aabb::new(center - Vec3::splat(half), center + Vec3::splat(half))
+    }
+
+    fn visible(frustum: &Frustum, aabb: &Aabb) -> bool {
+        let sphere = BoundingSphere::from_aabb(aabb);
+        frustum.intersects_sphere(&sphere) && frustum.intersects_aabb(aabb)
+    }
+
+    /// 90 degrees field of view from the origin looking down -Z
+    fn perspective() -> Frustum {
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        Frustum::from_view_proj(&(Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0) * view))
+    }
+
+    /// 10 × 10 box from the camera at z = 10 looking down -Z
+    fn orthographic() -> Frustum {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO, Vec3::Y);
+        Frustum::from_view_proj(&(Mat4::orthographic_rh(-5.0, 5.0, -5.0, 5.0, 0.1, 100.0) * view))
+    }
+
+    #[test]
+    fn perspective_inside_outside_and_straddling() {
+        let frustum = perspective();
+
+        assert!(visible(&frustum, &cube(Vec3::new(0.0, 0.0, -10.0), 1.0)));
+        assert!(visible(&frustum, &cube(Vec3::new(3.0, -3.0, -50.0), 1.0)));
+
+        // Behind, beside and past the far plane
+        assert!(!visible(&frustum, &cube(Vec3::new(0.0, 0.0, 10.0), 1.0)));
+        assert!(!visible(&frustum, &cube(Vec3::new(20.0, 0.0, -10.0), 1.0)));
+        assert!(!visible(&frustum, &cube(Vec3::new(0.0, -20.0, -10.0), 1.0)));
+        assert!(!visible(&frustum, &cube(Vec3::new(0.0, 0.0, -150.0), 1.0)));
+
+        // Across the right, near and far planes
+        assert!(visible(&frustum, &cube(Vec3::new(10.5, 0.0, -10.0), 1.0)));
+        assert!(visible(&frustum, &cube(Vec3::ZERO, 0.5)));
+        assert!(visible(&frustum, &cube(Vec3::new(0.0, 0.0, -100.0), 1.0)));
+    }
+
+    #[test]
+    fn orthographic_inside_outside_and_straddling() {
+        let frustum = orthographic();
+
+        assert!(visible(&frustum, &cube(Vec3::ZERO, 1.0)));
+        assert!(visible(&frustum, &cube(Vec3::new(4.0, 4.0, -80.0), 0.5)));
+
+        assert!(!visible(&frustum, &cube(Vec3::new(6.5, 0.0, 0.0), 1.0)));
+        assert!(!visible(&frustum, &cube(Vec3::new(0.0, -6.5, 0.0), 1.0)));
+        assert!(!visible(&frustum, &cube(Vec3::new(0.0, 0.0, 12.0), 1.0)));
+        assert!(!visible(&frustum, &cube(Vec3::new(0.0, 0.0, -95.0), 1.0)));
+
+        assert!(visible(&frustum, &cube(Vec3::new(5.5, 0.0, 0.0), 1.0)));
+        assert!(visible(&frustum, &cube(Vec3::new(0.0, 0.0, 10.0), 1.0)));
+        assert!(visible(&frustum, &cube(Vec3::new(0.0, 0.0, -90.0), 1.0)));
+    }
+
+    #[test]
+    fn aabb_outside_a_corner_is_culled_where_the_sphere_is_not() {
+        let frustum = orthographic();
+        // Beyond both the right and top planes, its sphere still reaches in
+        let aabb = Aabb::new(Vec3::new(5.1, 5.1, -1.0), Vec3::new(7.0, 7.0, 1.0));
+
+        assert!(frustum.intersects_sphere(&BoundingSphere::from_aabb(&aabb)));
+        assert!(!frustum.intersects_aabb(&aabb));
+    }
+
+    #[test]
+    fn aabb_transformed_by_rotation_and_translation() {
+        let aabb = cube(Vec3::ZERO, 1.0);
+        let matrix = Mat4::from_rotation_translation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4), Vec3::new(1.0, 2.0, 3.0));
+        let moved = aabb.transformed(&matrix);
+
+        let half = Vec3::new(2f32.sqrt(), 2f32.sqrt(), 1.0);
+        assert!(moved.center().abs_diff_eq(Vec3::new(1.0, 2.0, 3.0), 1e-5));
+        assert!(moved.half_extents().abs_diff_eq(half, 1e-5));
+
+        // Every transformed corner stays inside
+        for corner in 0..8 {
+            let p = Vec3::select(glam::BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0), aabb.max, aabb.min);
+            let p = matrix.transform_point3(p);
+            assert!(p.cmpge(moved.min - 1e-5).all() && p.cmple(moved.max + 1e-5).all());
+        }
+    }
+
+    #[test]
+    fn sphere_transformed_by_the_largest_scale() {
+        let sphere = BoundingSphere::new(Vec3::X, 1.0);
+        let matrix = Mat4::from_scale_rotation_translation(Vec3::new(1.0, 3.0, 2.0), Quat::from_rotation_y(1.0), Vec3::Y);
+        let moved = sphere.transformed(&matrix);
+
+        assert!((moved.radius - 3.0).abs() < 1e-5);
+        assert!(moved.center.abs_diff_eq(matrix.transform_point3(Vec3::X), 1e-5));
+    }
+}
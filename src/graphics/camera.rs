@@ -0,0 +1,392 @@
+use wgpu::Color;
+use winit::dpi::PhysicalSize;
+
+use super::{id, uniform_bind_group, CameraUniform, EntityId, GameWorld, GlobalTransform, Id, Stage, System, WebGPUType};
+use crate::{orthographic, perspective, Mat4, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// `fov_y` is the vertical field of view in radians
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// `height` is the visible height in world units, the width follows the aspect
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fov_y, near, far } => perspective(fov_y, aspect, near, far),
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect;
+                orthographic(-half_width, half_width, -half_height, half_height, near, far)
+            }
+        }
+    }
+}
+
+/// Part of the surface a camera draws to, in `0.0..=1.0` from the top-left
+/// corner so it follows the surface size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x:      f32,
+    pub y:      f32,
+    pub width:  f32,
+    pub height: f32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl Viewport {
+
+    pub const FULL: Self = Self { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// `(x, y, width, height)` in physical pixels of a surface
+    pub fn to_physical(&self, size: PhysicalSize<u32>) -> (f32, f32, f32, f32) {
+        let (w, h) = (size.width as f32, size.height as f32);
+        (self.x * w, self.y * h, self.width * w, self.height * h)
+    }
+
+    /// Viewport rectangle on a surface, clamped to it since wgpu rejects
+    /// viewports reaching outside the target. Drawn with [`crop`](Self::crop)
+    /// a viewport partly off the surface keeps its size and only loses the
+    /// part outside. `None` when nothing is left.
+    pub fn to_viewport(&self, size: PhysicalSize<u32>) -> Option<(f32, f32, f32, f32)> {
+        let (x, y, width, height) = self.to_physical(size);
+        let (w, h) = (size.width as f32, size.height as f32);
+
+        let (left, top) = (x.clamp(0.0, w), y.clamp(0.0, h));
+        let (right, bottom) = ((x + width).clamp(0.0, w), (y + height).clamp(0.0, h));
+
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some((left, top, right - left, bottom - top))
+    }
+
+    /// Clip space correction mapping the whole viewport onto the clamped
+    /// [`to_viewport`](Self::to_viewport) rectangle, identity when the
+    /// viewport lies within the surface
+    pub fn crop(&self, size: PhysicalSize<u32>) -> Mat4 {
+        let Some((left, top, visible_width, visible_height)) = self.to_viewport(size) else {
+            return Mat4::IDENTITY;
+        };
+        let (x, y, width, height) = self.to_physical(size);
+
+        // Centers in pixels, clip space `y` points up
+        let offset_x = (x + width / 2.0) - (left + visible_width / 2.0);
+        let offset_y = (y + height / 2.0) - (top + visible_height / 2.0);
+
+        Mat4::from_translation(Vec3::new(offset_x * 2.0 / visible_width, -offset_y * 2.0 / visible_height, 0.0))
+            * Mat4::from_scale(Vec3::new(width / visible_width, height / visible_height, 1.0))
+    }
+
+    /// Whole pixels covered on a surface, clamped to it, for the scissor rectangle.
+    /// `None` when nothing is left.
+    pub fn to_scissor(&self, size: PhysicalSize<u32>) -> Option<(u32, u32, u32, u32)> {
+        let (x, y, width, height) = self.to_physical(size);
+
+        let left = (x.max(0.0) as u32).min(size.width);
+        let top = (y.max(0.0) as u32).min(size.height);
+        let right = ((x + width).max(0.0).ceil() as u32).min(size.width);
+        let bottom = ((y + height).max(0.0).ceil() as u32).min(size.height);
+
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some((left, top, right - left, bottom - top))
+    }
+
+    pub fn is_full(&self) -> bool {
+        *self == Self::FULL
+    }
+}
+
+/// Bit mask of the layers an entity is drawn on, and of the layers a
+/// [`Camera`] draws. Entities without the component are on layer 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderLayers(pub u32);
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::layer(0)
+    }
+}
+
+impl RenderLayers {
+
+    pub const ALL: Self = Self(u32::MAX);
+    pub const NONE: Self = Self(0);
+
+    /// Number of layers, the methods below panic for an `n` not below it
+    pub const COUNT: u8 = 32;
+
+    /// Only layer `n`
+    pub const fn layer(n: u8) -> Self {
+        Self(Self::bit(n))
+    }
+
+    pub const fn with(self, n: u8) -> Self {
+        Self(self.0 | Self::bit(n))
+    }
+
+    pub const fn without(self, n: u8) -> Self {
+        Self(self.0 & !Self::bit(n))
+    }
+
+    pub const fn contains(&self, n: u8) -> bool {
+        self.0 & Self::bit(n) != 0
+    }
+
+    const fn bit(n: u8) -> u32 {
+        assert!(n < Self::COUNT, "Error render layer out of range 0..32");
+        1 << n
+    }
+
+    pub const fn intersects(&self, other: &RenderLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+/// Renders the scene as seen from the entity's [`GlobalTransform`], looking
+/// down its `-Z` axis. Without a transform the camera sits at the origin.
+///
+/// ```ignore
+/// let mut camera = world.create_entity();
+/// camera.add_component(Camera::perspective(60f32.to_radians(), 0.1, 100.0));
+/// camera.add_component(Transform::from_xyz(0.0, 0.0, 5.0));
+///
+/// // A minimap drawn over it, seeing only layer 1
+/// let mut minimap = world.create_entity();
+/// minimap.add_component(Camera::orthographic(50.0, 0.1, 100.0)
+///     .with_viewport(Viewport::new(0.75, 0.0, 0.25, 0.25))
+///     .with_priority(1)
+///     .with_render_layers(RenderLayers::layer(1)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub projection:     Projection,
+    /// Width over height, recomputed from the surface and viewport when `auto_aspect` is set
+    pub aspect:         f32,
+    pub auto_aspect:    bool,
+    pub viewport:       Viewport,
+    /// `None` keeps what was drawn before instead of clearing
+    pub clear_color:    Option<Color>,
+    pub is_active:      bool,
+    /// Cameras are drawn from the lowest to the highest priority
+    pub priority:       i32,
+    pub render_layers:  RenderLayers,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::perspective(45f32.to_radians(), 0.1, 1000.0)
+    }
+}
+
+impl Camera {
+
+    pub fn perspective(fov_y: f32, near: f32, far: f32) -> Self {
+        Self::new(Projection::Perspective { fov_y, near, far })
+    }
+
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Self {
+        Self::new(Projection::Orthographic { height, near, far })
+    }
+
+    pub fn new(projection: Projection) -> Self {
+        Self {
+            projection,
+            aspect:         1.0,
+            auto_aspect:    true,
+            viewport:       Viewport::FULL,
+            clear_color:    Some(Color::BLACK),
+            is_active:      true,
+            priority:       0,
+            render_layers:  RenderLayers::default(),
+        }
+    }
+
+    /// Fixed aspect ratio, no longer following the surface
+    pub fn with_aspect(mut self, aspect: f32) -> Self {
+        self.aspect = aspect;
+        self.auto_aspect = false;
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn with_clear_color(mut self, clear_color: Option<Color>) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_render_layers(mut self, render_layers: RenderLayers) -> Self {
+        self.render_layers = render_layers;
+        self
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        self.projection.matrix(self.aspect)
+    }
+
+    /// Inverse of the camera's world matrix
+    pub fn view_matrix(global: &GlobalTransform) -> Mat4 {
+        global.matrix().inverse()
+    }
+
+    pub fn view_proj(&self, global: &GlobalTransform) -> Mat4 {
+        self.projection_matrix() * Self::view_matrix(global)
+    }
+}
+
+/// Camera uniform buffer and its bind group, bound at group 0 by the built-in shaders
+#[derive(Debug)]
+pub struct ComponentCameraUniform {
+    pub buffer:     Id,
+    pub bind_group: Id,
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Keeps the aspect of every camera in sync with the surface and uploads
+    /// its view-projection to the camera bind group
+    pub fn update_cameras(&mut self) {
+        let size = self.resource.borrow().ctx.size.get();
+
+        if size.width > 0 && size.height > 0 {
+            for mut camera in self.query_mut::<&mut Camera>() {
+                let (_, _, width, height) = camera.viewport.to_physical(size);
+                let aspect = width / height.max(1.0);
+                if camera.auto_aspect && camera.aspect != aspect {
+                    camera.aspect = aspect;
+                }
+            }
+        }
+
+        let cameras: Vec<(EntityId, Mat4, Option<Id>)> = self
+            .query::<(EntityId, &Camera, Option<&GlobalTransform>, Option<&ComponentCameraUniform>)>()
+            .map(|(id, camera, global, uniform)| {
+                let view_proj = camera.viewport.crop(size) * camera.view_proj(global.unwrap_or(&GlobalTransform::default()));
+                (id, view_proj, uniform.map(|u| u.buffer))
+            })
+            .collect();
+
+        let mut created = vec![];
+        {
+            let mut res = self.resource.borrow_mut();
+            let res = &mut *res;
+
+            for (entity, view_proj, buffer) in cameras {
+                let uniform = CameraUniform::new(view_proj);
+
+                match buffer {
+                    Some(buffer) => res.ctx.queue.write_buffer(&res.uniform_buffer[&buffer], 0, uniform.bytes()),
+                    None => {
+                        let (buffer, bind_group) = uniform_bind_group(
+                            &res.ctx.device,
+                            &res.camera_bind_group_layout,
+                            "Camera Uniform",
+                            uniform.bytes(),
+                        );
+
+                        let component = ComponentCameraUniform { buffer: id(), bind_group: id() };
+                        res.uniform_buffer.insert(component.buffer, buffer);
+                        res.bind_group.insert(component.bind_group, bind_group);
+                        created.push((entity, component));
+                    }
+                }
+            }
+        }
+
+        for (entity, component) in created {
+            self.add_component(entity, component);
+        }
+    }
+
+    /// Active cameras in drawing order, lowest priority first. Cameras of the
+    /// same priority keep their spawn order.
+    pub fn active_cameras(&self) -> Vec<(&Camera, &ComponentCameraUniform, Option<&GlobalTransform>)> {
+        let mut cameras: Vec<_> = self
+            .query::<(&Camera, &ComponentCameraUniform, Option<&GlobalTransform>)>()
+            .filter(|(camera, _, _)| camera.is_active)
+            .collect();
+
+        cameras.sort_by_key(|(camera, _, _)| camera.priority);
+        cameras
+    }
+
+    /// Registers the camera update in [`Stage::PostUpdate`] after transform propagation
+    pub(crate) fn add_camera_systems(&mut self) {
+        self.add_system(Stage::PostUpdate, System::new("camera_update", |world| world.update_cameras())
+            .query::<(&mut Camera, &GlobalTransform, &mut ComponentCameraUniform)>()
+            .after("transform_propagate"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_layers_cover_all_32_layers() {
+        let layers = RenderLayers::NONE.with(0).with(31);
+        assert_eq!(layers, RenderLayers(1 | 1 << 31));
+        assert!(layers.contains(31));
+        assert!(!layers.contains(30));
+        assert_eq!(layers.without(31), RenderLayers::layer(0));
+        assert!(RenderLayers::ALL.contains(RenderLayers::COUNT - 1));
+    }
+
+    #[test]
+    fn viewport_inside_the_surface_is_not_cropped() {
+        let size = PhysicalSize::new(200, 100);
+        let viewport = Viewport::new(0.5, 0.0, 0.5, 0.5);
+        assert_eq!(viewport.to_viewport(size), Some((100.0, 0.0, 100.0, 50.0)));
+        assert_eq!(viewport.crop(size), Mat4::IDENTITY);
+        assert_eq!(Viewport::FULL.crop(size), Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn viewport_off_the_surface_is_cropped_not_squeezed() {
+        let size = PhysicalSize::new(100, 100);
+
+        // Right half past the right edge: clip `x` 0 is the surface edge
+        let viewport = Viewport::new(0.5, 0.0, 1.0, 1.0);
+        assert_eq!(viewport.to_viewport(size), Some((50.0, 0.0, 50.0, 100.0)));
+        assert_eq!(viewport.to_scissor(size), Some((50, 0, 50, 100)));
+        let crop = viewport.crop(size);
+        assert!(crop.transform_point3(Vec3::new(-1.0, 1.0, 0.5)).abs_diff_eq(Vec3::new(-1.0, 1.0, 0.5), 1e-6));
+        assert!(crop.transform_point3(Vec3::new(0.0, -1.0, 0.5)).abs_diff_eq(Vec3::new(1.0, -1.0, 0.5), 1e-6));
+
+        // Top half above the surface: clip `y` 0 is the top edge
+        let viewport = Viewport::new(0.0, -0.5, 1.0, 1.0);
+        let crop = viewport.crop(size);
+        assert!(crop.transform_point3(Vec3::new(1.0, 0.0, 0.5)).abs_diff_eq(Vec3::new(1.0, 1.0, 0.5), 1e-6));
+        assert!(crop.transform_point3(Vec3::new(1.0, -1.0, 0.5)).abs_diff_eq(Vec3::new(1.0, -1.0, 0.5), 1e-6));
+
+        assert_eq!(Viewport::new(1.0, 0.0, 0.5, 0.5).to_viewport(size), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn render_layer_32_panics() {
+        RenderLayers::layer(RenderLayers::COUNT);
+    }
+}
@@ -0,0 +1,161 @@
+use winit::dpi::PhysicalSize;
+use winit::window::{Window, WindowBuilder};
+
+/// Where the winit canvas ends up in the page.
+#[derive(Debug, Clone)]
+pub enum CanvasMount {
+    /// Append the canvas to the first element matching a CSS selector
+    AppendTo(String),
+    /// Take the place of an existing `<canvas>` with this id
+    CanvasId(String),
+    /// Take the place of an existing `<canvas>` element
+    #[cfg(target_arch = "wasm32")]
+    Canvas(web_sys::HtmlCanvasElement),
+    /// Leave the canvas out of the document, the caller mounts it
+    Detached,
+}
+
+/// Resolution the surface is rendered at, relative to the canvas CSS size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PixelRatio {
+    /// Follow `window.devicePixelRatio`, sharp on HiDPI screens
+    Device,
+    /// Render at a fixed ratio of CSS pixels, `1.0` trades sharpness for fill rate
+    Fixed(f64),
+}
+
+impl PixelRatio {
+    pub fn apply(&self, size: PhysicalSize<u32>, scale_factor: f64) -> PhysicalSize<u32> {
+        match *self {
+            PixelRatio::Device => size,
+            PixelRatio::Fixed(ratio) => {
+                let scale = ratio / scale_factor;
+                PhysicalSize::new(
+                    ((size.width as f64 * scale).round() as u32).max(1),
+                    ((size.height as f64 * scale).round() as u32).max(1),
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CanvasOptions {
+    pub mount:       CanvasMount,
+    pub pixel_ratio: PixelRatio,
+    pub css_width:   Option<String>,
+    pub css_height:  Option<String>,
+}
+
+impl Default for CanvasOptions {
+    fn default() -> Self {
+        Self {
+            mount:       CanvasMount::AppendTo("#main-body".to_string()),
+            pixel_ratio: PixelRatio::Device,
+            css_width:   None,
+            css_height:  None,
+        }
+    }
+}
+
+impl CanvasOptions {
+
+    pub fn append_to(mut self, selector: &str) -> Self {
+        self.mount = CanvasMount::AppendTo(selector.to_string());
+        self
+    }
+
+    pub fn canvas_id(mut self, id: &str) -> Self {
+        self.mount = CanvasMount::CanvasId(id.to_string());
+        self
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn canvas(mut self, canvas: web_sys::HtmlCanvasElement) -> Self {
+        self.mount = CanvasMount::Canvas(canvas);
+        self
+    }
+
+    pub fn detached(mut self) -> Self {
+        self.mount = CanvasMount::Detached;
+        self
+    }
+
+    pub fn pixel_ratio(mut self, pixel_ratio: PixelRatio) -> Self {
+        self.pixel_ratio = pixel_ratio;
+        self
+    }
+
+    /// CSS `width` / `height` of the canvas, e.g. `"100%"` or `"640px"`.
+    /// The surface follows the resulting size through winit resize events.
+    pub fn css_size(mut self, width: &str, height: &str) -> Self {
+        self.css_width = Some(width.to_string());
+        self.css_height = Some(height.to_string());
+        self
+    }
+
+    /// Lets winit render straight into an existing `<canvas>` instead of
+    /// creating its own. Without this the existing element is replaced on mount.
+    pub fn window_builder(&self, builder: WindowBuilder) -> WindowBuilder {
+        #[cfg(target_arch = "wasm32")] {
+            use winit::platform::web::WindowBuilderExtWebSys;
+            if let Some(canvas) = self.existing_canvas() {
+                return builder.with_canvas(Some(canvas));
+            }
+        }
+        builder
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn existing_canvas(&self) -> Option<web_sys::HtmlCanvasElement> {
+        use wasm_bindgen::JsCast;
+        match &self.mount {
+            CanvasMount::Canvas(canvas) => Some(canvas.clone()),
+            CanvasMount::CanvasId(id) => {
+                let doc = web_sys::window()?.document()?;
+                doc.get_element_by_id(id)?.dyn_into::<web_sys::HtmlCanvasElement>().ok()
+            }
+            _ => None
+        }
+    }
+
+    /// Puts the canvas of the window into the page, does nothing on native
+    pub(crate) fn mount(&self, #[cfg_attr(not(target_arch = "wasm32"), allow(unused_variables))] window: &Window) {
+        #[cfg(target_arch = "wasm32")] {
+            use winit::platform::web::WindowExtWebSys;
+
+            let doc = web_sys::window().unwrap().document().unwrap();
+            let canvas = window.canvas().expect("Error get window canvas");
+
+            match &self.mount {
+                CanvasMount::AppendTo(selector) => {
+                    let parent = doc.query_selector(selector)
+                        .ok()
+                        .flatten()
+                        .unwrap_or_else(|| panic!("Error find canvas parent `{}`", selector));
+                    parent.append_child(&canvas).expect("Error append canvas");
+                }
+                CanvasMount::CanvasId(_) | CanvasMount::Canvas(_) => {
+                    let existing = self.existing_canvas().expect("Error find existing canvas");
+                    let node: &web_sys::Node = &canvas;
+                    if !existing.is_same_node(Some(node)) {
+                        if !existing.id().is_empty() {
+                            canvas.set_id(&existing.id());
+                        }
+                        canvas.set_class_name(&existing.class_name());
+                        existing.replace_with_with_node_1(&canvas).expect("Error replace canvas");
+                    }
+                }
+                CanvasMount::Detached => {}
+            }
+
+            let style = canvas.style();
+            if let Some(width) = &self.css_width {
+                style.set_property("width", width).expect("Error set canvas width");
+            }
+            if let Some(height) = &self.css_height {
+                style.set_property("height", height).expect("Error set canvas height");
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use super::{Entity, EntityId, GameWorld};
+
+type Command<W> = Box<dyn FnOnce(&mut W)>;
+
+/// Queue of world changes applied later, at the end of the current stage.
+///
+/// Cloning shares the queue, so a handle can be taken before iterating a
+/// query and used inside the loop.
+///
+/// ```ignore
+/// let commands = world.commands();
+/// for (id, health) in world.query::<(EntityId, &Health)>() {
+///     if health.0 <= 0.0 {
+///         commands.despawn(id);
+///     }
+/// }
+/// ```
+pub struct Commands<W> {
+    queue: Rc<RefCell<VecDeque<Command<W>>>>,
+}
+
+impl<W> Clone for Commands<W> {
+    fn clone(&self) -> Self {
+        Self { queue: self.queue.clone() }
+    }
+}
+
+impl<W> Default for Commands<W> {
+    fn default() -> Self {
+        Self { queue: Rc::default() }
+    }
+}
+
+/// World changes made by the built-in [`Commands`], implemented by [`GameWorld`].
+/// [`Commands::spawn`] builds a [`GameWorld`] [`Entity`] and is not part of it.
+pub trait CommandWorld {
+
+    fn apply_despawn(&mut self, id: EntityId);
+    fn apply_insert<T: 'static>(&mut self, id: EntityId, component: T);
+    fn apply_remove<T: 'static>(&mut self, id: EntityId);
+}
+
+impl<W> Commands<W> {
+
+    /// Runs any change on the world when the queue is applied
+    pub fn add(&self, command: impl FnOnce(&mut W) + 'static) {
+        self.queue.borrow_mut().push_back(Box::new(command));
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.borrow().is_empty()
+    }
+
+    fn pop(&self) -> Option<Command<W>> {
+        self.queue.borrow_mut().pop_front()
+    }
+
+    /// Applies queued commands in order, including commands they queue
+    fn apply(&self, world: &mut W) {
+        while let Some(command) = self.pop() {
+            command(world);
+        }
+    }
+}
+
+impl<W: CommandWorld> Commands<W> {
+
+    pub fn despawn(&self, id: EntityId) {
+        self.add(move |world| world.apply_despawn(id));
+    }
+
+    /// Adds a component, ignored if the entity was despawned in the meantime
+    pub fn insert<T: 'static>(&self, id: EntityId, component: T) {
+        self.add(move |world| world.apply_insert(id, component));
+    }
+
+    pub fn remove<T: 'static>(&self, id: EntityId) {
+        self.add(move |world| world.apply_remove::<T>(id));
+    }
+}
+
+impl Commands<GameWorld<'_>> {
+
+    /// Creates an entity and lets `build` add its components
+    pub fn spawn(&self, build: impl FnOnce(&mut Entity) + 'static) {
+        self.add(move |world| build(&mut world.create_entity()));
+    }
+}
+
+impl CommandWorld for GameWorld<'_> {
+
+    fn apply_despawn(&mut self, id: EntityId) {
+        self.despawn(id);
+    }
+
+    fn apply_insert<T: 'static>(&mut self, id: EntityId, component: T) {
+        self.add_component(id, component);
+    }
+
+    fn apply_remove<T: 'static>(&mut self, id: EntityId) {
+        self.remove_component::<T>(id);
+    }
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Handle to the world's command queue
+    pub fn commands(&self) -> Commands<Self> {
+        self.commands.clone()
+    }
+
+    /// Applies queued commands in order, including commands they queue.
+    /// Called by the scheduler after every stage and by the [`App`](crate::App)
+    /// runner after every group of callbacks.
+    pub fn apply_commands(&mut self) {
+        self.commands.clone().apply(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Components, Entities};
+
+    /// The entity bookkeeping of a [`GameWorld`] without its GPU resources
+    #[derive(Default)]
+    struct World {
+        entities:   Entities,
+        components: Components,
+        log:        Vec<&'static str>,
+    }
+
+    impl CommandWorld for World {
+
+        fn apply_despawn(&mut self, id: EntityId) {
+            if self.entities.free(id) {
+                self.components.remove_all(id);
+            }
+        }
+
+        fn apply_insert<T: 'static>(&mut self, id: EntityId, component: T) {
+            if self.entities.is_alive(id) {
+                self.components.insert(id, component);
+            }
+        }
+
+        fn apply_remove<T: 'static>(&mut self, id: EntityId) {
+            self.components.remove::<T>(id);
+        }
+    }
+
+    #[test]
+    fn deferred_changes_apply_in_order() {
+        let mut world = World::default();
+        let (a, b) = (world.entities.alloc(), world.entities.alloc());
+        let commands = Commands::default();
+
+        commands.insert(a, 1u32);
+        commands.remove::<u32>(a);
+        commands.insert(a, 2u32);
+        commands.insert(b, 3u32);
+        commands.despawn(b);
+        commands.insert(b, 4u32);
+        commands.add(|world: &mut World| {
+            let c = world.entities.alloc();
+            world.components.insert(c, 5u32);
+        });
+        assert_eq!(commands.len(), 7);
+        assert_eq!(world.components.get::<u32>(a), None);
+
+        commands.apply(&mut world);
+
+        assert!(commands.is_empty());
+        assert_eq!(world.components.get::<u32>(a), Some(&2));
+        assert!(!world.entities.is_alive(b));
+        assert_eq!(world.components.get::<u32>(b), None);
+        assert_eq!(world.components.column::<u32>().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn commands_queued_while_applying_run_last() {
+        let mut world = World::default();
+        let commands = Commands::default();
+
+        let queue = commands.clone();
+        commands.add(move |world: &mut World| {
+            world.log.push("first");
+            queue.add(|world: &mut World| world.log.push("queued by first"));
+        });
+        commands.add(|world: &mut World| world.log.push("second"));
+
+        commands.apply(&mut world);
+        assert_eq!(world.log, ["first", "second", "queued by first"]);
+    }
+}
@@ -0,0 +1,303 @@
+use winit::keyboard::KeyCode;
+use winit::window::CursorGrabMode;
+
+use super::{Camera, GameWorld, Projection, Stage, System, Transform};
+use crate::{EulerRot, Input, MouseButton, Quat, Time, Vec3, PIXELS_PER_LINE};
+
+const MAX_PITCH: f32 = 89f32 * std::f32::consts::PI / 180.0;
+
+/// Rotates the camera around `target` while `rotate_button` is held and
+/// zooms with the mouse wheel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitController {
+    pub target:         Vec3,
+    pub distance:       f32,
+    /// Radians around `Y`
+    pub yaw:            f32,
+    /// Radians above the horizon
+    pub pitch:          f32,
+    pub rotate_button:  MouseButton,
+    /// Radians per pixel of mouse motion
+    pub rotate_speed:   f32,
+    /// Fraction of the distance per wheel line
+    pub zoom_speed:     f32,
+    pub min_distance:   f32,
+    pub max_distance:   f32,
+}
+
+impl OrbitController {
+
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw:            0.0,
+            pitch:          0.0,
+            rotate_button:  MouseButton::Left,
+            rotate_speed:   0.005,
+            zoom_speed:     0.1,
+            min_distance:   0.1,
+            max_distance:   1000.0,
+        }
+    }
+
+    pub fn with_angles(mut self, yaw: f32, pitch: f32) -> Self {
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        self
+    }
+
+    fn update(&mut self, input: &Input, transform: &mut Transform) {
+        if input.mouse_pressed(self.rotate_button) {
+            let (dx, dy) = input.cursor_delta();
+            self.yaw -= dx as f32 * self.rotate_speed;
+            self.pitch = (self.pitch + dy as f32 * self.rotate_speed).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        let scroll = input.scroll_lines().1 + input.scroll_pixels().1 as f32 / PIXELS_PER_LINE;
+        if scroll != 0.0 {
+            self.distance *= 1.0 - scroll * self.zoom_speed;
+            self.distance = self.distance.clamp(self.min_distance, self.max_distance);
+        }
+
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, -self.pitch, 0.0);
+        transform.translation = self.target + transform.rotation * Vec3::new(0.0, 0.0, self.distance);
+    }
+}
+
+/// First person camera: mouse look while the pointer is locked, moves with
+/// the keys relative to where it looks.
+///
+/// Clicking `grab_button` locks the pointer, `Escape` releases it.
+/// `locked` follows the real lock state, which also ends when the window
+/// loses focus or the browser exits the pointer lock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlyController {
+    pub yaw:            f32,
+    pub pitch:          f32,
+    /// World units per second
+    pub speed:          f32,
+    /// Speed multiplier while `boost` is held
+    pub boost_factor:   f32,
+    /// Radians per unit of raw mouse motion
+    pub sensitivity:    f32,
+    pub forward:        KeyCode,
+    pub back:           KeyCode,
+    pub left:           KeyCode,
+    pub right:          KeyCode,
+    pub up:             KeyCode,
+    pub down:           KeyCode,
+    pub boost:          KeyCode,
+    pub grab_button:    MouseButton,
+    pub locked:         bool,
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        Self::new(5.0)
+    }
+}
+
+impl FlyController {
+
+    pub fn new(speed: f32) -> Self {
+        Self {
+            yaw:            0.0,
+            pitch:          0.0,
+            speed,
+            boost_factor:   3.0,
+            sensitivity:    0.002,
+            forward:        KeyCode::KeyW,
+            back:           KeyCode::KeyS,
+            left:           KeyCode::KeyA,
+            right:          KeyCode::KeyD,
+            up:             KeyCode::KeyE,
+            down:           KeyCode::KeyQ,
+            boost:          KeyCode::ShiftLeft,
+            grab_button:    MouseButton::Left,
+            locked:         false,
+        }
+    }
+
+    pub fn with_angles(mut self, yaw: f32, pitch: f32) -> Self {
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        self
+    }
+
+    /// Returns the lock state to request when it should change
+    fn update(&mut self, input: &Input, delta: f32, transform: &mut Transform) -> Option<bool> {
+        let mut grab = None;
+
+        if !self.locked && input.mouse_just_pressed(self.grab_button) {
+            grab = Some(true);
+        } else if self.locked && input.key_just_pressed(KeyCode::Escape) {
+            grab = Some(false);
+        }
+
+        if self.locked {
+            let (dx, dy) = input.mouse_motion();
+            self.yaw -= dx as f32 * self.sensitivity;
+            self.pitch = (self.pitch - dy as f32 * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0);
+
+        let axis = |positive: KeyCode, negative: KeyCode| {
+            input.key_pressed(positive) as i32 as f32 - input.key_pressed(negative) as i32 as f32
+        };
+
+        let direction = Vec3::new(
+            axis(self.right, self.left),
+            axis(self.up, self.down),
+            axis(self.back, self.forward),
+        );
+
+        if direction != Vec3::ZERO {
+            let speed = if input.key_pressed(self.boost) { self.speed * self.boost_factor } else { self.speed };
+            transform.translation += transform.rotation * direction.normalize() * speed * delta;
+        }
+
+        grab
+    }
+}
+
+/// 2D camera for orthographic projections: drags the view with
+/// `pan_button` and zooms towards the cursor with the mouse wheel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PanZoomController {
+    pub pan_button:     MouseButton,
+    /// Fraction of the visible height per wheel line
+    pub zoom_speed:     f32,
+    pub min_height:     f32,
+    pub max_height:     f32,
+}
+
+impl Default for PanZoomController {
+    fn default() -> Self {
+        Self {
+            pan_button:     MouseButton::Right,
+            zoom_speed:     0.1,
+            min_height:     0.01,
+            max_height:     10000.0,
+        }
+    }
+}
+
+impl PanZoomController {
+
+    fn update(&self, input: &Input, camera: &mut Camera, transform: &mut Transform) {
+        let Projection::Orthographic { height, near, far } = camera.projection else {
+            return;
+        };
+
+        let window_height = input.window_size().height.max(1) as f32;
+        let units_per_pixel = height / window_height;
+
+        if input.mouse_pressed(self.pan_button) {
+            let (dx, dy) = input.cursor_delta();
+            let offset = transform.right() * -dx as f32 + transform.up() * dy as f32;
+            transform.translation += offset * units_per_pixel;
+        }
+
+        let scroll = input.scroll_lines().1 + input.scroll_pixels().1 as f32 / PIXELS_PER_LINE;
+        if scroll == 0.0 {
+            return;
+        }
+
+        let zoomed = (height * (1.0 - scroll * self.zoom_speed)).clamp(self.min_height, self.max_height);
+
+        // Keep the world point under the cursor in place
+        if let Some((x, y)) = input.cursor_ndc() {
+            let half = Vec3::new(x * camera.aspect, y, 0.0) * 0.5;
+            let before = transform.right() * half.x * height + transform.up() * half.y * height;
+            let after = transform.right() * half.x * zoomed + transform.up() * half.y * zoomed;
+            transform.translation += before - after;
+        }
+
+        camera.projection = Projection::Orthographic { height: zoomed, near, far };
+    }
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Applies the input of this frame to every camera controller
+    pub fn update_camera_controllers(&mut self) {
+        let delta = self.resource::<Time>().delta();
+
+        self.resource_scope(|world, input: &mut Input| {
+            for (mut orbit, mut transform) in world.query_mut::<(&mut OrbitController, &mut Transform)>() {
+                orbit.update(input, &mut transform);
+            }
+
+            let mut grab = None;
+            for (mut fly, mut transform) in world.query_mut::<(&mut FlyController, &mut Transform)>() {
+                grab = fly.update(input, delta, &mut transform).or(grab);
+            }
+
+            for (pan_zoom, mut camera, mut transform) in world.query_mut::<(&PanZoomController, &mut Camera, &mut Transform)>() {
+                pan_zoom.update(input, &mut camera, &mut transform);
+            }
+
+            let requested = grab.map(|grab| set_pointer_lock(world, grab));
+            if let Some(locked) = pointer_lock_state(world, input, requested) {
+                for mut fly in world.query_mut::<&mut FlyController>() {
+                    fly.locked = locked;
+                }
+            }
+        });
+    }
+
+    /// Registers the controller update in [`Stage::Update`], done once by [`GameWorld::new`]
+    pub(crate) fn add_controller_systems(&mut self) {
+        self.add_system(Stage::Update, System::new("camera_controllers", |world| world.update_camera_controllers())
+            .query::<(&mut OrbitController, &mut FlyController, &mut Camera, &mut Transform)>()
+            .reads_resource::<Input>()
+            .reads_resource::<Time>());
+    }
+}
+
+/// Locks and hides the pointer, or releases it, and returns whether it is
+/// locked afterwards. On the web this requests a pointer lock on the canvas,
+/// which browsers only grant during a click and some time later.
+fn set_pointer_lock(world: &GameWorld, lock: bool) -> bool {
+    let res = world.resource.borrow();
+    let window = res.ctx.window;
+
+    if lock {
+        let locked = window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+
+        if let Err(e) = locked {
+            log::warn!("Error lock pointer: {}", e);
+            return false;
+        }
+        window.set_cursor_visible(false);
+        true
+    } else {
+        if let Err(e) = window.set_cursor_grab(CursorGrabMode::None) {
+            log::warn!("Error release pointer: {}", e);
+        }
+        window.set_cursor_visible(true);
+        false
+    }
+}
+
+/// Lock state of the pointer when it is known to differ from the one of the
+/// controllers: the result of a request, or the lock lost with the focus.
+/// On the web the document tells, since the browser exits the lock on its own.
+#[cfg(not(target_arch = "wasm32"))]
+fn pointer_lock_state(world: &GameWorld, input: &Input, requested: Option<bool>) -> Option<bool> {
+    let locked = world.query::<&FlyController>().any(|fly| fly.locked);
+    if !input.is_focused() && locked {
+        return Some(set_pointer_lock(world, false));
+    }
+    requested
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pointer_lock_state(_world: &GameWorld, _input: &Input, _requested: Option<bool>) -> Option<bool> {
+    let document = web_sys::window().and_then(|w| w.document())?;
+    Some(document.pointer_lock_element().is_some())
+}
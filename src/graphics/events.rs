@@ -0,0 +1,234 @@
+use std::marker::PhantomData;
+
+use super::GameWorld;
+
+/// Double-buffered queue of events of one type, stored as a resource.
+///
+/// Events stay readable for the frame they were sent in and the next one,
+/// so readers running before the writer in a frame still see them.
+pub struct Events<T> {
+    previous:   Vec<(usize, T)>,
+    current:    Vec<(usize, T)>,
+    count:      usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous:   vec![],
+            current:    vec![],
+            count:      0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+
+    pub fn send(&mut self, event: T) {
+        self.current.push((self.count, event));
+        self.count += 1;
+    }
+
+    /// Drops the events of the previous frame, called once per frame
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// Reader that sees every event still stored
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader::default()
+    }
+
+    /// Reader that only sees events sent from now on
+    pub fn reader_current(&self) -> EventReader<T> {
+        EventReader { last: self.count, marker: PhantomData }
+    }
+
+    /// Every stored event, oldest first, without a cursor
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(&self.current).map(|(_, e)| e)
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+}
+
+/// Cursor into an [`Events`] queue, owned by the system that reads.
+///
+/// ```ignore
+/// let mut resized = EventReader::<WindowResized>::default();
+/// world.add_system(Stage::Update, System::new("on_resize", move |world| {
+///     for event in resized.read(world.events::<WindowResized>()) {
+///         log::info!("{}x{}", event.width, event.height);
+///     }
+/// }));
+/// ```
+pub struct EventReader<T> {
+    last:   usize,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self { last: 0, marker: PhantomData }
+    }
+}
+
+impl<T> EventReader<T> {
+
+    /// Events sent since the previous call, oldest first
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let last = self.last;
+        self.last = events.count;
+
+        events.previous
+            .iter()
+            .chain(&events.current)
+            .filter(move |(id, _)| *id >= last)
+            .map(|(_, e)| e)
+    }
+
+    /// Number of unread events
+    pub fn len(&self, events: &Events<T>) -> usize {
+        events.previous.iter().chain(&events.current).filter(|(id, _)| *id >= self.last).count()
+    }
+
+    pub fn is_empty(&self, events: &Events<T>) -> bool {
+        self.len(events) == 0
+    }
+
+    /// Marks every stored event as read
+    pub fn clear(&mut self, events: &Events<T>) {
+        self.last = events.count;
+    }
+}
+
+/// Sends events of one type, borrowed from the world.
+pub struct EventWriter<'w, T> {
+    events: &'w mut Events<T>,
+}
+
+impl<T> EventWriter<'_, T> {
+
+    pub fn send(&mut self, event: T) {
+        self.events.send(event);
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        for event in events {
+            self.events.send(event);
+        }
+    }
+}
+
+/// Sent by [`GameWorld::resize`] with the new surface size in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowResized {
+    pub width:  u32,
+    pub height: u32,
+}
+
+/// Sent by the [`App`](crate::App) runner when the window is asked to close,
+/// readable from the shutdown callbacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowCloseRequested;
+
+/// Update functions of every registered event type
+#[derive(Default)]
+struct EventUpdaters(Vec<fn(&mut GameWorld)>);
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Registers an event type, its queue is updated every frame by
+    /// [`update_events`](Self::update_events). Adding it twice does nothing.
+    pub fn add_event<T: 'static>(&mut self) {
+        if self.contains_resource::<Events<T>>() {
+            return;
+        }
+
+        self.insert_resource(Events::<T>::default());
+        self.init_resource::<EventUpdaters>();
+        self.resource_mut::<EventUpdaters>().0.push(|world| world.resource_mut::<Events<T>>().update());
+    }
+
+    /// Panics if `T` was not registered with [`add_event`](Self::add_event)
+    pub fn events<T: 'static>(&self) -> &Events<T> {
+        self.resource::<Events<T>>()
+    }
+
+    pub fn events_mut<T: 'static>(&mut self) -> &mut Events<T> {
+        self.resource_mut::<Events<T>>()
+    }
+
+    pub fn event_writer<T: 'static>(&mut self) -> EventWriter<'_, T> {
+        EventWriter { events: self.events_mut::<T>() }
+    }
+
+    pub fn send_event<T: 'static>(&mut self, event: T) {
+        self.events_mut::<T>().send(event);
+    }
+
+    /// Swaps the buffers of every event type, called by the [`App`](crate::App)
+    /// runner at the end of every frame
+    pub fn update_events(&mut self) {
+        let Some(updaters) = self.get_resource::<EventUpdaters>() else {
+            return;
+        };
+
+        for update in updaters.0.clone() {
+            update(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_live_for_exactly_one_update() {
+        let mut events = Events::default();
+        let mut reader = events.reader();
+
+        events.send(1);
+        events.update();
+        events.send(2);
+        assert_eq!(reader.len(&events), 2);
+        assert_eq!(reader.read(&events).copied().collect::<Vec<_>>(), [1, 2]);
+        assert!(reader.is_empty(&events));
+
+        // A late reader still sees the previous frame's event once
+        let mut late = events.reader();
+        events.update();
+        assert_eq!(late.read(&events).copied().collect::<Vec<_>>(), [2]);
+
+        events.update();
+        assert!(events.is_empty());
+        assert!(late.is_empty(&events));
+        assert_eq!(reader.read(&events).count(), 0);
+    }
+
+    #[test]
+    fn reader_current_skips_earlier_events() {
+        let mut events = Events::default();
+        events.send("old");
+
+        let mut reader = events.reader_current();
+        events.send("new");
+        assert_eq!(reader.read(&events).copied().collect::<Vec<_>>(), ["new"]);
+
+        events.send("newer");
+        reader.clear(&events);
+        assert!(reader.is_empty(&events));
+    }
+}
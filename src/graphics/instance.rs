@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+
+use wgpu::{BufferDescriptor, BufferUsages, PrimitiveTopology};
+
+use super::{
+    id, mesh_instanced_pipeline, mesh_instanced_shader, Aabb, AlphaMode, BoundingSphere, ComponentMesh, ComponentRenderPipelineMesh,
+    ComponentRenderPipelineMeshInstanced, Entity, EntityId, GameResource, GameWorld, GlobalTransform, Id, InstanceRaw, NoFrustumCulling,
+    RenderLayers, Stage, System, WebGPUType, With, Without,
+};
+use crate::{Mat4, Vec4};
+
+/// Draws the entity as one more instance of `source`, which holds the mesh
+/// and the [`ComponentRenderPipelineMeshInstanced`]. Only the transform and
+/// the [`InstanceColor`] of the entity are used.
+///
+/// ```ignore
+/// let mut cube = world.create_entity();
+/// cube.add_mesh(vertex, Some(index));
+/// cube.add_shader_mesh_instanced();
+/// cube.add_mesh_instanced_pipeline(PrimitiveTopology::TriangleList);
+/// let cube = cube.id();
+///
+/// for i in 0..10_000 {
+///     let mut copy = world.create_entity();
+///     copy.add_instance_of(cube);
+///     copy.add_component(Transform::from_xyz(i as f32, 0.0, 0.0));
+///     copy.add_component(InstanceColor::rgba(1.0, 0.5, 0.5, 1.0));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceOf(pub EntityId);
+
+/// Color multiplied with the vertex color of an instance, white without it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceColor(pub Vec4);
+
+impl Default for InstanceColor {
+    fn default() -> Self {
+        Self::WHITE
+    }
+}
+
+impl InstanceColor {
+
+    pub const WHITE: Self = Self(Vec4::ONE);
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self(Vec4::new(r, g, b, a))
+    }
+}
+
+/// Instance vertex buffer of an instanced source entity, rewritten every
+/// frame with the source first and then its [`InstanceOf`] entities
+#[derive(Debug)]
+pub struct ComponentInstances {
+    pub buffer:     Id,
+    pub count:      u32,
+    pub capacity:   u32,
+    /// World bounds of every instance together, `None` with [`NoFrustumCulling`]
+    pub bounds:     Option<(Aabb, BoundingSphere)>,
+}
+
+pub trait SystemInstance {
+    fn add_instance_of(&mut self, source: EntityId);
+}
+
+impl SystemInstance for Entity<'_, '_> {
+    fn add_instance_of(&mut self, source: EntityId) {
+        self.add_component(InstanceOf(source));
+    }
+}
+
+/// Opts an opaque entity drawn with an ordinary [`ComponentRenderPipelineMesh`]
+/// into the [`MeshBatches`]. Batched entities are drawn with the built-in
+/// instanced mesh shader instead of their own [`ComponentShaderMesh`](super::ComponentShaderMesh).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AutoBatch;
+
+/// [`AutoBatch`] entities whose meshes have the same
+/// [`geometry`](ComponentMesh::geometry) and topology and that share their
+/// render layers, drawn as instances of the first one in a single draw.
+/// Rebuilt every frame by [`GameWorld::batch_meshes`], groups of fewer than
+/// [`MeshBatches::MIN_BATCH`] entities are drawn on their own.
+#[derive(Debug, Default)]
+pub struct MeshBatches {
+    pub batches:    Vec<MeshBatch>,
+    /// Entities drawn by one of `batches` rather than on their own
+    pub batched:    HashSet<EntityId>,
+    /// Instanced mesh shader, made for the first batch
+    shader:         Option<Id>,
+    /// Pipeline of the batches of every topology
+    pipelines:      HashMap<PrimitiveTopology, Id>,
+    /// Instance buffers of last frame's batches, reused while their key stays
+    buffers:        HashMap<BatchKey, (Id, u32)>,
+}
+
+impl MeshBatches {
+    pub const MIN_BATCH: usize = 2;
+}
+
+/// One group of [`MeshBatches`], drawn with the mesh of the entity `mesh`
+#[derive(Debug)]
+pub struct MeshBatch {
+    pub mesh:       EntityId,
+    pub pipeline:   Id,
+    pub layers:     RenderLayers,
+    pub instances:  ComponentInstances,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BatchKey {
+    geometry:   u64,
+    topology:   PrimitiveTopology,
+    layers:     RenderLayers,
+    culled:     bool,
+}
+
+/// Instances of one source collected in [`GameWorld::upload_instances`] and
+/// [`GameWorld::batch_meshes`]
+struct Batch {
+    data:   Vec<InstanceRaw>,
+    buffer: Option<(Id, u32)>,
+    /// Local bounds of the mesh, and the world bounds of the instances so far
+    local:  Aabb,
+    bounds: Option<Aabb>,
+}
+
+impl Batch {
+    fn new(mesh: &ComponentMesh, buffer: Option<(Id, u32)>, culling: Option<&NoFrustumCulling>) -> Self {
+        Self {
+            data:   vec![],
+            buffer,
+            local:  mesh.aabb,
+            bounds: culling.is_none().then_some(mesh.aabb),
+        }
+    }
+
+    fn push(&mut self, global: Option<&GlobalTransform>, color: Option<&InstanceColor>) {
+        let model = global.map_or(Mat4::IDENTITY, |g| g.0);
+        let color = color.copied().unwrap_or_default();
+        self.data.push(InstanceRaw::new(model, color.0.to_array()));
+
+        let local = self.local.transformed(&model);
+        self.bounds = self.bounds.map(|bounds| match self.data.len() {
+            1 => local,
+            _ => bounds.union(&local),
+        });
+    }
+
+    /// Writes the instances into the batch buffer, replacing it with a larger
+    /// one when they no longer fit
+    fn upload(self, res: &mut GameResource) -> ComponentInstances {
+        let count = self.data.len() as u32;
+        let bounds = self.bounds.map(|aabb| (aabb, BoundingSphere::from_aabb(&aabb)));
+
+        match self.buffer {
+            Some((buffer, capacity)) if count <= capacity => {
+                res.ctx.queue.write_buffer(&res.vertex_buffer[&buffer], 0, self.data.bytes());
+                ComponentInstances { buffer, count, capacity, bounds }
+            }
+            previous => {
+                // Grow by powers of two so a spawning swarm doesn't reallocate every frame
+                let capacity = count.next_power_of_two();
+                let instance_buffer = res.ctx.device.create_buffer(&BufferDescriptor {
+                    label:              Some("Instance Buffer"),
+                    size:               capacity as u64 * std::mem::size_of::<InstanceRaw>() as u64,
+                    usage:              BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                res.ctx.queue.write_buffer(&instance_buffer, 0, self.data.bytes());
+
+                if let Some((old, _)) = previous {
+                    res.vertex_buffer.remove(&old);
+                }
+
+                let buffer = id();
+                res.vertex_buffer.insert(buffer, instance_buffer);
+                ComponentInstances { buffer, count, capacity, bounds }
+            }
+        }
+    }
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Collects the transform and color of every instance into the instance
+    /// buffer of its source, growing the buffer when it no longer fits
+    pub fn upload_instances(&mut self) {
+        let mut batches: HashMap<EntityId, Batch> = HashMap::new();
+
+        for (entity, mesh, _, global, color, instances, culling) in self.query::<(
+            EntityId,
+            &ComponentMesh,
+            &ComponentRenderPipelineMeshInstanced,
+            Option<&GlobalTransform>,
+            Option<&InstanceColor>,
+            Option<&ComponentInstances>,
+            Option<&NoFrustumCulling>,
+        )>() {
+            let mut batch = Batch::new(mesh, instances.map(|i| (i.buffer, i.capacity)), culling);
+            batch.push(global, color);
+            batches.insert(entity, batch);
+        }
+
+        for (InstanceOf(source), global, color) in self.query::<(&InstanceOf, Option<&GlobalTransform>, Option<&InstanceColor>)>() {
+            if let Some(batch) = batches.get_mut(source) {
+                batch.push(global, color);
+            }
+        }
+
+        let components: Vec<(EntityId, ComponentInstances)> = {
+            let mut res = self.resource.borrow_mut();
+            batches.into_iter().map(|(entity, batch)| (entity, batch.upload(&mut res))).collect()
+        };
+
+        // Updated in place, replacing the component would free its buffer
+        for (entity, component) in components {
+            match self.components.get_mut::<ComponentInstances>(entity) {
+                Some(current) => *current = component,
+                None => self.add_component(entity, component),
+            }
+        }
+    }
+
+    /// Groups the [`AutoBatch`] mesh entities into the [`MeshBatches`]
+    /// resource and uploads the transforms of every batch
+    pub fn batch_meshes(&mut self) {
+        let mut groups: HashMap<BatchKey, (Vec<EntityId>, Batch)> = HashMap::new();
+
+        for (entity, mesh, pipeline, global, layers, culling) in self.query_filtered::<(
+            EntityId,
+            &ComponentMesh,
+            &ComponentRenderPipelineMesh,
+            Option<&GlobalTransform>,
+            Option<&RenderLayers>,
+            Option<&NoFrustumCulling>,
+        ), (With<AutoBatch>, Without<InstanceOf>)>() {
+            // Blended meshes are sorted one by one, back to front
+            if pipeline.alpha_mode.is_transparent() {
+                continue;
+            }
+
+            let key = BatchKey {
+                geometry:   mesh.geometry,
+                topology:   pipeline.topology,
+                layers:     layers.copied().unwrap_or_default(),
+                culled:     culling.is_none(),
+            };
+            let (members, batch) = groups.entry(key).or_insert_with(|| (vec![], Batch::new(mesh, None, culling)));
+            members.push(entity);
+            batch.push(global, None);
+        }
+
+        groups.retain(|_, (members, _)| members.len() >= MeshBatches::MIN_BATCH);
+
+        let mut batches = self.remove_resource::<MeshBatches>().unwrap_or_default();
+        {
+            let mut res = self.resource.borrow_mut();
+            let res = &mut *res;
+
+            let shader = *batches.shader.get_or_insert_with(|| {
+                let shader = id();
+                res.shader.insert(shader, mesh_instanced_shader(&res.ctx.device));
+                shader
+            });
+
+            // Buffers of the groups gone since last frame
+            for (key, (buffer, _)) in &batches.buffers {
+                if !groups.contains_key(key) {
+                    res.vertex_buffer.remove(buffer);
+                }
+            }
+
+            let mut buffers = HashMap::new();
+            batches.batches.clear();
+            batches.batched.clear();
+
+            for (key, (members, mut batch)) in groups {
+                let pipeline = *batches.pipelines.entry(key.topology).or_insert_with(|| {
+                    let pipeline = id();
+                    let render_pipeline = mesh_instanced_pipeline(res, &res.shader[&shader], AlphaMode::Opaque, key.topology);
+                    res.render_pipeline.insert(pipeline, render_pipeline);
+                    pipeline
+                });
+
+                batch.buffer = batches.buffers.get(&key).copied();
+                let instances = batch.upload(res);
+
+                buffers.insert(key, (instances.buffer, instances.capacity));
+                batches.batched.extend(&members);
+                batches.batches.push(MeshBatch { mesh: members[0], pipeline, layers: key.layers, instances });
+            }
+            batches.buffers = buffers;
+        }
+
+        self.insert_resource(batches);
+    }
+
+    /// Registers the instance upload and the mesh batching in
+    /// [`Stage::PostUpdate`] after transform propagation
+    pub(crate) fn add_instance_systems(&mut self) {
+        self.add_system(Stage::PostUpdate, System::new("instance_upload", |world| world.upload_instances())
+            .query::<(&GlobalTransform, &InstanceOf, &InstanceColor, &ComponentMesh, &mut ComponentInstances)>()
+            .after("transform_propagate"));
+
+        self.add_system(Stage::PostUpdate, System::new("mesh_batch", |world| world.batch_meshes())
+            .query::<(&GlobalTransform, &ComponentMesh, &ComponentRenderPipelineMesh, &RenderLayers, &AutoBatch)>()
+            .writes_resource::<MeshBatches>()
+            .after("transform_propagate"));
+        self.insert_resource(MeshBatches::default());
+    }
+}
@@ -1,73 +1,259 @@
-use wgpu::util::BufferInitDescriptor;
-use wgpu::util::DeviceExt;
-use wgpu::BufferUsages;
-
-use super::id;
-use super::Id;
-use super::Vertex3D;
-use super::Entity;
-use super::WebGPUType;
-
-#[derive(Debug)]
-pub struct ComponentMesh {
-    pub vertex: Vec<Vertex3D>,
-    pub vertex_buffer: Id,
-    pub index_buffer:  Option<Id>,
-    pub indeces:       Option<Vec<u16>>
-}
-
-impl ComponentMesh {
-    fn new(entity: &Entity, vertex: Vec<Vertex3D>, indeces: Option<Vec<u16>>) -> Self {
-
-        let mut res = entity.game_resource.borrow_mut();
-
-        let vertex_buffer = res.ctx.device.create_buffer_init(&BufferInitDescriptor {
-            label:      None,
-            contents:   vertex.bytes(),
-            usage:      BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
-
-        let v_id = id();
-        res.vertex_buffer.insert(v_id, vertex_buffer);
-
-        match indeces {
-            Some(index) => {
-
-                let index_buffer = res.ctx.device.create_buffer_init(&BufferInitDescriptor {
-                    label:      None,
-                    contents:   vertex.bytes(),
-                    usage:      BufferUsages::VERTEX | BufferUsages::COPY_DST,
-                });
-
-                let indeces_id = id();
-                res.index_buffer.insert(indeces_id, index_buffer);
-
-                Self {
-                    vertex,
-                    vertex_buffer:  v_id,
-                    index_buffer:   Some(indeces_id),
-                    indeces:        Some(index),
-                }
-            }
-
-            None => {
-                Self {
-                    vertex,
-                    vertex_buffer: v_id,
-                    index_buffer: None,
-                    indeces: None,
-                }
-            }
-        }
-    }
-}
-
-pub trait SystemMesh {
-    fn add_mesh(&mut self, vertex: Vec<Vertex3D>, indeces: Option<Vec<u16>>);
-}
-
-impl SystemMesh for Entity<'_> {
-    fn add_mesh(&mut self, vertex: Vec<Vertex3D>, indexes: Option<Vec<u16>>) {
-        self.add_component(ComponentMesh::new(self, vertex, indexes));
-    }
-}
\ No newline at end of file
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use wgpu::util::BufferInitDescriptor;
+use wgpu::util::DeviceExt;
+use wgpu::BufferUsages;
+use wgpu::{Buffer, BufferDescriptor, Device, Queue};
+
+use super::id;
+use super::EntityId;
+use super::GameWorld;
+use super::Id;
+use super::Vertex3D;
+use super::Entity;
+use super::{Aabb, BoundingSphere};
+use super::WebGPUType;
+use crate::Vec3;
+
+#[derive(Debug)]
+pub struct ComponentMesh {
+    pub vertex: Vec<Vertex3D>,
+    pub vertex_buffer: Id,
+    pub index_buffer:  Option<Id>,
+    pub indeces:       Option<Vec<u16>>,
+    /// Local bounds of `vertex`, see [`ComponentMesh::update_bounds`]
+    pub aabb:          Aabb,
+    pub sphere:        BoundingSphere,
+    /// Hash of the vertices and indices, meshes with the same geometry are
+    /// drawn together by the [`MeshBatches`](super::MeshBatches)
+    pub geometry:      u64,
+}
+
+impl ComponentMesh {
+    fn new(entity: &Entity, vertex: Vec<Vertex3D>, indeces: Option<Vec<u16>>) -> Self {
+
+        let mut res = entity.game_resource().borrow_mut();
+
+        let vertex_buffer = res.ctx.device.create_buffer_init(&BufferInitDescriptor {
+            label:      None,
+            contents:   vertex.bytes(),
+            usage:      BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+
+        let v_id = id();
+        res.vertex_buffer.insert(v_id, vertex_buffer);
+
+        match indeces {
+            Some(index) => {
+
+                let index_buffer = res.ctx.device.create_buffer_init(&BufferInitDescriptor {
+                    label:      None,
+                    contents:   bytemuck::cast_slice(&index),
+                    usage:      BufferUsages::INDEX | BufferUsages::COPY_DST,
+                });
+
+                let indeces_id = id();
+                res.index_buffer.insert(indeces_id, index_buffer);
+
+                Self {
+                    vertex,
+                    vertex_buffer:  v_id,
+                    index_buffer:   Some(indeces_id),
+                    indeces:        Some(index),
+                    aabb:           Aabb::default(),
+                    sphere:         BoundingSphere::default(),
+                    geometry:       0,
+                }.with_bounds()
+            }
+
+            None => {
+                Self {
+                    vertex,
+                    vertex_buffer: v_id,
+                    index_buffer: None,
+                    indeces: None,
+                    aabb: Aabb::default(),
+                    sphere: BoundingSphere::default(),
+                    geometry: 0,
+                }.with_bounds()
+            }
+        }
+    }
+}
+
+impl ComponentMesh {
+
+    /// Recomputes `aabb` and `sphere` from the vertex positions
+    pub fn update_bounds(&mut self) {
+        let vertex = &self.vertex;
+        let points = || vertex.iter().map(|v| Vec3::from_array(v.pos));
+        self.aabb = Aabb::from_points(points());
+        self.sphere = BoundingSphere::from_points(&self.aabb, points());
+    }
+
+    /// Recomputes `geometry` from the vertices and indices
+    pub fn update_geometry(&mut self) {
+        let mut hasher = DefaultHasher::new();
+        self.vertex.bytes().hash(&mut hasher);
+        self.indeces.hash(&mut hasher);
+        self.geometry = hasher.finish();
+    }
+
+    fn with_bounds(mut self) -> Self {
+        self.update_bounds();
+        self.update_geometry();
+        self
+    }
+}
+
+pub trait SystemMesh {
+    fn add_mesh(&mut self, vertex: Vec<Vertex3D>, indeces: Option<Vec<u16>>);
+}
+
+impl SystemMesh for Entity<'_, '_> {
+    fn add_mesh(&mut self, vertex: Vec<Vertex3D>, indexes: Option<Vec<u16>>) {
+        self.add_component(ComponentMesh::new(self, vertex, indexes));
+    }
+}
+
+/// Writes `bytes` at the start of `buffer`, replacing it with a buffer of the
+/// next power of two size when they don't fit. Returns the new buffer.
+fn write_or_grow(device: &Device, queue: &Queue, buffer: &Buffer, usage: BufferUsages, bytes: &[u8]) -> Option<Buffer> {
+    if bytes.len() as u64 <= buffer.size() {
+        queue.write_buffer(buffer, 0, bytes);
+        return None;
+    }
+
+    let grown = device.create_buffer(&BufferDescriptor {
+        label:              None,
+        size:               (bytes.len() as u64).next_power_of_two(),
+        usage,
+        mapped_at_creation: false,
+    });
+    queue.write_buffer(&grown, 0, bytes);
+    Some(grown)
+}
+
+/// Indices as bytes padded to the 4 byte copy alignment
+fn index_bytes(indices: &[u16]) -> Vec<u8> {
+    let mut bytes = bytemuck::cast_slice(indices).to_vec();
+    bytes.resize(bytes.len().next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT as usize), 0);
+    bytes
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Replaces the vertices of the entity's mesh, reusing its vertex buffer
+    /// when they fit and growing it when they don't
+    pub fn set_vertices(&mut self, entity: EntityId, vertex: Vec<Vertex3D>) {
+        let Some(mesh) = self.components.get_mut::<ComponentMesh>(entity) else {
+            log::warn!("Error set vertices: entity has no ComponentMesh");
+            return;
+        };
+
+        mesh.vertex = vertex;
+        mesh.update_bounds();
+        mesh.update_geometry();
+
+        let mut res = self.resource.borrow_mut();
+        let res = &mut *res;
+        let usage = BufferUsages::VERTEX | BufferUsages::COPY_DST;
+
+        if let Some(grown) = write_or_grow(&res.ctx.device, &res.ctx.queue, &res.vertex_buffer[&mesh.vertex_buffer], usage, mesh.vertex.bytes()) {
+            res.vertex_buffer.insert(mesh.vertex_buffer, grown);
+        }
+    }
+
+    /// Overwrites the vertices from `start` on, in place. The mesh can't grow
+    /// this way, vertices past its end are ignored.
+    pub fn update_vertices(&mut self, entity: EntityId, start: usize, vertex: &[Vertex3D]) {
+        let Some(mesh) = self.components.get_mut::<ComponentMesh>(entity) else {
+            log::warn!("Error update vertices: entity has no ComponentMesh");
+            return;
+        };
+
+        let end = (start + vertex.len()).min(mesh.vertex.len());
+        if start >= end {
+            return;
+        }
+
+        mesh.vertex[start..end].copy_from_slice(&vertex[..end - start]);
+        mesh.update_bounds();
+        mesh.update_geometry();
+
+        let res = self.resource.borrow();
+        let offset = (start * std::mem::size_of::<Vertex3D>()) as u64;
+        res.ctx.queue.write_buffer(&res.vertex_buffer[&mesh.vertex_buffer], offset, bytemuck::cast_slice(&mesh.vertex[start..end]));
+    }
+
+    /// Replaces the indices of the entity's mesh, `None` draws the vertices
+    /// in order. The index buffer is reused when they fit.
+    pub fn set_indices(&mut self, entity: EntityId, indices: Option<Vec<u16>>) {
+        let Some(mesh) = self.components.get_mut::<ComponentMesh>(entity) else {
+            log::warn!("Error set indices: entity has no ComponentMesh");
+            return;
+        };
+
+        let mut res = self.resource.borrow_mut();
+        let res = &mut *res;
+
+        match (&indices, mesh.index_buffer) {
+            (Some(index), Some(buffer)) => {
+                let usage = BufferUsages::INDEX | BufferUsages::COPY_DST;
+                if let Some(grown) = write_or_grow(&res.ctx.device, &res.ctx.queue, &res.index_buffer[&buffer], usage, &index_bytes(index)) {
+                    res.index_buffer.insert(buffer, grown);
+                }
+            }
+            (Some(index), None) => {
+                let index_buffer = res.ctx.device.create_buffer_init(&BufferInitDescriptor {
+                    label:      None,
+                    contents:   bytemuck::cast_slice(index),
+                    usage:      BufferUsages::INDEX | BufferUsages::COPY_DST,
+                });
+
+                let buffer = id();
+                res.index_buffer.insert(buffer, index_buffer);
+                mesh.index_buffer = Some(buffer);
+            }
+            (None, Some(buffer)) => {
+                res.index_buffer.remove(&buffer);
+                mesh.index_buffer = None;
+            }
+            (None, None) => (),
+        }
+
+        mesh.indeces = indices;
+        mesh.update_geometry();
+    }
+
+    /// Overwrites the indices from `start` on, in place, like [`GameWorld::update_vertices`]
+    pub fn update_indices(&mut self, entity: EntityId, start: usize, indices: &[u16]) {
+        let Some(mesh) = self.components.get_mut::<ComponentMesh>(entity) else {
+            log::warn!("Error update indices: entity has no ComponentMesh");
+            return;
+        };
+        let (Some(current), Some(buffer)) = (mesh.indeces.as_mut(), mesh.index_buffer) else {
+            return;
+        };
+
+        let end = (start + indices.len()).min(current.len());
+        if start >= end {
+            return;
+        }
+        current[start..end].copy_from_slice(&indices[..end - start]);
+
+        // Copies start and end on 4 bytes, i.e. on even indices
+        let aligned_start = start & !1;
+        let aligned_end = ((end + 1) & !1).min(current.len());
+        let mut range = current[aligned_start..aligned_end].to_vec();
+        if range.len() % 2 == 1 {
+            range.push(0);
+        }
+        mesh.update_geometry();
+
+        let res = self.resource.borrow();
+        let offset = (aligned_start * std::mem::size_of::<u16>()) as u64;
+        res.ctx.queue.write_buffer(&res.index_buffer[&buffer], offset, bytemuck::cast_slice(&range));
+    }
+}
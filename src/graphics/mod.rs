@@ -1,191 +1,378 @@
-use winit::dpi::PhysicalSize;
-use winit::window::Window;
-use std::any::Any;
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
-use wgpu::*;
-
-mod context;
-pub use context::*;
-
-mod types;
-pub use types::*;
-
-mod texture_mesh;
-pub use texture_mesh::*;
-
-mod mesh;
-pub use mesh::*;
-
-mod uniform;
-pub use uniform::*;
-
-mod shader;
-pub use shader::*;
-
-mod bind_group;
-pub use bind_group::*;
-
-mod pipeline;
-pub use pipeline::*;
-
-mod random;
-pub use random::*;
-
-mod render;
-pub use render::*;
-
-use log::warn;
-type Id = i64;
-
-pub struct Entity< 'p> {
-    pub game_resource: Rc<RefCell<GameResource< 'p>>>,
-    pub components: Vec<Box<dyn Any>>
-}
-
-impl Drop for Entity<'_> {
-    fn drop(&mut self) {
-        warn!("WARN! Enityt is drop, but resources not free! {}", self.components.len());
-    }
-}
-
-impl< 'p> Entity< 'p> {
-
-    pub fn add_component<T: 'static>(&mut self, component: T) {
-        self.components.push(Box::new(component));
-    }
-
-    pub fn get_component<T: 'static>(&self) -> Option<&T> {
-        for i in &self.components {
-            if let Some(x) = i.downcast_ref::<T>() {
-                return Some(x);
-            }
-        }
-        None
-    }
-
-    pub fn get_mut_component<T: 'static>(&mut self) -> Option<&mut T>{
-        for i in &mut self.components {
-            if let Some(x) = i.downcast_mut::<T>() {
-                return Some(x);
-            }
-        }
-        None
-    }
-
-    pub fn get_mut_components<T: 'static>(&mut self) -> Option<Vec<&mut T>>{
-
-        let mut v: Option<Vec<&mut T>> = None;
-
-        for i in &mut self.components {
-            if let Some(x) = i.downcast_mut::<T>() {
-                match &mut v {
-                    None => { v = Some(vec![x]) }
-                    Some(n) => { n.push(x) }
-                }
-            }
-        }
-
-        v
-    }
-
-    pub fn get_components<T: 'static>(&self) -> Option<Vec<&T>>{
-
-        let mut v: Option<Vec<&T>> = None;
-
-        for i in &self.components {
-            if let Some(x) = i.downcast_ref::<T>() {
-                match &mut v {
-                    None => { v = Some(vec![x]) }
-                    Some(n) => { n.push(x) }
-                }
-            }
-        }
-
-        v
-    }
-
-    pub fn remove_component<T: 'static>(&mut self) {
-        for i in 0..self.components.len() {
-            if let Some(x) = self.components[i].downcast_ref::<T>() {
-                self.components.remove(i);
-                return;
-            }
-        }
-    }
-
-    pub fn remove_components<T: 'static>(&mut self) {
-        for i in 0..self.components.len() {
-            if let Some(x) = self.components[i].downcast_ref::<T>() {
-                self.components.remove(i);
-            }
-        }
-    }
-
-    pub fn new(game_resource: Rc<RefCell<GameResource< 'p>>>) -> Self {
-        Self {
-            game_resource,
-            components: vec![]
-        }
-    }
-}
-
-pub struct GameResource<'s> {
-    pub ctx:                        Rc<WebGPUContext<'s>>,
-    pub render_pipeline:            HashMap<Id, RenderPipeline>,
-    pub vertex_buffer:              HashMap<Id, Buffer>,
-    pub index_buffer:               HashMap<Id, Buffer>,
-    pub indeces:                    HashMap<Id, Vec<u16>>,
-    pub bind_group:                 HashMap<Id, BindGroup>,
-    pub bind_group_layout:          HashMap<Id, BindGroupLayout>,
-    pub vertex_buffer_layout:       HashMap<Id, VertexBufferLayout<'static>>,
-    pub uniform_buffer:             HashMap<Id, Buffer>,
-    pub shader:                     HashMap<Id, ShaderModule>,
-    pub texture_buffer:             HashMap<Id, Buffer>,
-}
-
-impl< 'p> GameResource< 'p> {
-    async fn new(ctx: WebGPUContext<'p>, window: &'p Window) -> Rc<RefCell<Self>> {
-        Rc::new(RefCell::new(Self {
-            ctx:                        ctx.into(),
-            vertex_buffer:              HashMap::new(),
-            vertex_buffer_layout:       HashMap::new(),
-            uniform_buffer:             HashMap::new(),
-            indeces:                    HashMap::new(),
-            index_buffer:               HashMap::new(),
-            shader:                     HashMap::new(),
-            render_pipeline:            HashMap::new(),
-            bind_group:                 HashMap::new(),
-            bind_group_layout:          HashMap::new(),
-            texture_buffer:             HashMap::new(),
-        }))
-    }
-}
-
-pub struct GameWorld< 'p> {
-    pub resource:   Rc<RefCell<GameResource< 'p>>>,
-    pub entity:     Vec<Entity< 'p>>
-}
-
-impl< 'p> GameWorld< 'p> {
-    pub async fn new(ctx: WebGPUContext<'p>, window: &'p Window) -> Self {
-        Self {
-            resource: GameResource::new(ctx, window).await,
-            entity: vec![]
-        }
-    }
-
-    pub fn create_entity(&self) -> Entity< 'p> {
-        Entity::new(self.resource.clone())
-    }
-
-    pub fn resize(&self, size: PhysicalSize<u32>) {
-        self.resource.borrow().ctx.resize(size);
-    }
-}
-
-
-
-
-
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wgpu::*;
+
+mod context;
+pub use context::*;
+
+mod canvas;
+pub use canvas::*;
+
+mod types;
+pub use types::*;
+
+mod texture_mesh;
+pub use texture_mesh::*;
+
+mod mesh;
+pub use mesh::*;
+
+mod uniform;
+pub use uniform::*;
+
+mod shader;
+pub use shader::*;
+
+mod bind_group;
+pub use bind_group::*;
+
+mod pipeline;
+pub use pipeline::*;
+
+mod random;
+pub use random::*;
+
+mod render;
+pub use render::*;
+
+mod storage;
+pub use storage::*;
+
+mod query;
+pub use query::*;
+
+mod schedule;
+pub use schedule::*;
+
+mod resources;
+pub use resources::*;
+
+mod events;
+pub use events::*;
+
+mod commands;
+pub use commands::*;
+
+mod transform;
+pub use transform::*;
+
+mod camera;
+pub use camera::*;
+
+mod controller;
+pub use controller::*;
+
+mod instance;
+pub use instance::*;
+
+mod bounds;
+pub use bounds::*;
+
+mod ring;
+pub use ring::*;
+
+pub mod shapes;
+
+use crate::{ActionMap, FixedTimestep, Input, Time};
+type Id = i64;
+
+/// Handle to one entity of a [`GameWorld`], used to add and read its components.
+pub struct Entity< 'w, 'p> {
+    id:     EntityId,
+    world:  &'w mut GameWorld< 'p>,
+}
+
+impl< 'w, 'p> Entity< 'w, 'p> {
+
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    pub fn world(&self) -> &GameWorld< 'p> {
+        self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut GameWorld< 'p> {
+        self.world
+    }
+
+    pub fn game_resource(&self) -> &Rc<RefCell<GameResource< 'p>>> {
+        &self.world.resource
+    }
+
+    /// Adds a component, replacing a previous component of the same type
+    pub fn add_component<T: 'static>(&mut self, component: T) {
+        if let Some(previous) = self.world.components.insert(self.id, component) {
+            self.world.release_gpu(&previous);
+        }
+    }
+
+    pub fn get_component<T: 'static>(&self) -> Option<&T> {
+        self.world.components.get::<T>(self.id)
+    }
+
+    pub fn get_mut_component<T: 'static>(&mut self) -> Option<&mut T> {
+        self.world.components.get_mut::<T>(self.id)
+    }
+
+    pub fn has_component<T: 'static>(&self) -> bool {
+        self.world.components.contains::<T>(self.id)
+    }
+
+    /// Removes a component, see [`GameWorld::remove_component`]
+    pub fn remove_component<T: 'static>(&mut self) -> Option<T> {
+        self.world.remove_component::<T>(self.id)
+    }
+}
+
+pub struct GameResource<'s> {
+    pub ctx:                        Rc<WebGPUContext<'s>>,
+    pub render_pipeline:            HashMap<Id, RenderPipeline>,
+    pub vertex_buffer:              HashMap<Id, Buffer>,
+    pub index_buffer:               HashMap<Id, Buffer>,
+    pub indeces:                    HashMap<Id, Vec<u16>>,
+    pub bind_group:                 HashMap<Id, BindGroup>,
+    pub bind_group_layout:          HashMap<Id, BindGroupLayout>,
+    pub vertex_buffer_layout:       HashMap<Id, VertexBufferLayout<'static>>,
+    pub uniform_buffer:             HashMap<Id, Buffer>,
+    pub shader:                     HashMap<Id, ShaderModule>,
+    pub texture_buffer:             HashMap<Id, Buffer>,
+    pub camera_bind_group_layout:   BindGroupLayout,
+    pub model_bind_group_layout:    BindGroupLayout,
+    /// Camera bind group used when no [`Camera`] is active
+    pub identity_camera:            BindGroup,
+    /// Model bind group of entities drawn without a [`GlobalTransform`]
+    pub identity_model:             BindGroup,
+    pub clear_pipeline:             RenderPipeline,
+    pub clear_depth_pipeline:       RenderPipeline,
+}
+
+impl< 'p> GameResource< 'p> {
+    async fn new(ctx: WebGPUContext<'p>, window: &'p Window) -> Rc<RefCell<Self>> {
+        let identity = bytemuck::cast_slice(&crate::to_gpu(crate::Mat4::IDENTITY)).to_vec();
+
+        let camera_bind_group_layout = uniform_bind_group_layout(&ctx.device, "Camera Bind Group Layout");
+        let (_, identity_camera) = uniform_bind_group(&ctx.device, &camera_bind_group_layout, "Camera Uniform", &identity);
+
+        let model_bind_group_layout = uniform_bind_group_layout(&ctx.device, "Model Bind Group Layout");
+        let (_, identity_model) = uniform_bind_group(&ctx.device, &model_bind_group_layout, "Model Uniform", &identity);
+
+        let clear_depth_pipeline = clear_pipeline(&ctx, false);
+        let clear_pipeline = clear_pipeline(&ctx, true);
+
+        Rc::new(RefCell::new(Self {
+            ctx:                        ctx.into(),
+            vertex_buffer:              HashMap::new(),
+            vertex_buffer_layout:       HashMap::new(),
+            uniform_buffer:             HashMap::new(),
+            indeces:                    HashMap::new(),
+            index_buffer:               HashMap::new(),
+            shader:                     HashMap::new(),
+            render_pipeline:            HashMap::new(),
+            bind_group:                 HashMap::new(),
+            bind_group_layout:          HashMap::new(),
+            texture_buffer:             HashMap::new(),
+            camera_bind_group_layout,
+            model_bind_group_layout,
+            identity_camera,
+            identity_model,
+            clear_pipeline,
+            clear_depth_pipeline,
+        }))
+    }
+
+    /// Drops the GPU objects a built-in component points to, called when the
+    /// component is removed, replaced or its entity despawned
+    pub fn release(&mut self, component: &dyn Any) {
+        if let Some(mesh) = component.downcast_ref::<ComponentMesh>() {
+            self.vertex_buffer.remove(&mesh.vertex_buffer);
+            if let Some(index_buffer) = mesh.index_buffer {
+                self.index_buffer.remove(&index_buffer);
+            }
+        } else if let Some(model) = component.downcast_ref::<ComponentModel>() {
+            self.uniform_buffer.remove(&model.buffer);
+            self.bind_group.remove(&model.bind_group);
+        } else if let Some(camera) = component.downcast_ref::<ComponentCameraUniform>() {
+            self.uniform_buffer.remove(&camera.buffer);
+            self.bind_group.remove(&camera.bind_group);
+        } else if let Some(uniform) = component.downcast_ref::<ComponentUniform>() {
+            for u in &uniform.uniforms {
+                self.uniform_buffer.remove(&u.buffer);
+            }
+        } else if let Some(instances) = component.downcast_ref::<ComponentInstances>() {
+            self.vertex_buffer.remove(&instances.buffer);
+        } else if let Some(pipeline) = component.downcast_ref::<ComponentRenderPipelineMesh>() {
+            self.render_pipeline.remove(&pipeline.id);
+        } else if let Some(pipeline) = component.downcast_ref::<ComponentRenderPipelineMeshUniform>() {
+            self.render_pipeline.remove(&pipeline.id);
+            self.bind_group.remove(&pipeline.bind_group);
+        } else if let Some(pipeline) = component.downcast_ref::<ComponentRenderPipelineMeshInstanced>() {
+            self.render_pipeline.remove(&pipeline.id);
+        } else if let Some(shader) = component.downcast_ref::<ComponentShaderMesh>() {
+            self.shader.remove(&shader.id);
+        } else if let Some(shader) = component.downcast_ref::<ComponentShaderMeshUniform>() {
+            self.shader.remove(&shader.id);
+        } else if let Some(shader) = component.downcast_ref::<ComponentShaderMeshInstanced>() {
+            self.shader.remove(&shader.id);
+        }
+    }
+}
+
+/// `true` for the built-in components pointing at objects in [`GameResource`]
+fn is_gpu_component(component: &dyn Any) -> bool {
+    component.is::<ComponentMesh>()
+        || component.is::<ComponentModel>()
+        || component.is::<ComponentCameraUniform>()
+        || component.is::<ComponentUniform>()
+        || component.is::<ComponentInstances>()
+        || component.is::<ComponentRenderPipelineMesh>()
+        || component.is::<ComponentRenderPipelineMeshUniform>()
+        || component.is::<ComponentRenderPipelineMeshInstanced>()
+        || component.is::<ComponentShaderMesh>()
+        || component.is::<ComponentShaderMeshUniform>()
+        || component.is::<ComponentShaderMeshInstanced>()
+}
+
+pub struct GameWorld< 'p> {
+    pub resource:   Rc<RefCell<GameResource< 'p>>>,
+    pub entities:   Entities,
+    pub components: Components,
+    pub resources:  Resources,
+    pub schedule:   Schedule,
+    commands:       Commands<GameWorld< 'p>>,
+}
+
+impl< 'p> GameWorld< 'p> {
+    pub async fn new(ctx: WebGPUContext<'p>, window: &'p Window) -> Self {
+        let mut world = Self {
+            resource: GameResource::new(ctx, window).await,
+            entities: Entities::default(),
+            components: Components::default(),
+            resources: Resources::default(),
+            schedule: Schedule::default(),
+            commands: Commands::default(),
+        };
+
+        world.insert_resource(Time::new());
+        world.insert_resource(FixedTimestep::default());
+        world.insert_resource(Input::default());
+        world.insert_resource(ActionMap::default());
+        world.insert_resource(RenderSettings::default());
+        world.add_event::<WindowResized>();
+        world.add_event::<WindowCloseRequested>();
+        world.add_transform_systems();
+        world.add_camera_systems();
+        world.add_controller_systems();
+        world.add_instance_systems();
+        world.add_render_graph();
+        world
+    }
+
+    pub fn create_entity(&mut self) -> Entity<'_, 'p> {
+        let id = self.entities.alloc();
+        Entity { id, world: self }
+    }
+
+    pub fn entity_mut(&mut self, id: EntityId) -> Option<Entity<'_, 'p>> {
+        if !self.entities.is_alive(id) {
+            return None;
+        }
+        Some(Entity { id, world: self })
+    }
+
+    /// Removes the entity and drops all of its components, its children
+    /// become root entities. Returns `false` if it was already despawned.
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        if !self.entities.is_alive(id) {
+            return false;
+        }
+
+        self.remove_parent(id);
+        if let Some(children) = self.components.remove::<Children>(id) {
+            for child in children.0 {
+                self.components.remove::<Parent>(child);
+            }
+        }
+
+        self.entities.free(id);
+        for component in self.components.remove_all(id) {
+            self.release_gpu(&*component);
+        }
+        true
+    }
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        self.entities.is_alive(id)
+    }
+
+    pub fn get_component<T: 'static>(&self, id: EntityId) -> Option<&T> {
+        self.components.get::<T>(id)
+    }
+
+    pub fn get_mut_component<T: 'static>(&mut self, id: EntityId) -> Option<&mut T> {
+        self.components.get_mut::<T>(id)
+    }
+
+    /// Adds a component to a live entity, replacing one of the same type
+    pub fn add_component<T: 'static>(&mut self, id: EntityId, component: T) {
+        if !self.entities.is_alive(id) {
+            return;
+        }
+        if let Some(previous) = self.components.insert(id, component) {
+            self.release_gpu(&previous);
+        }
+    }
+
+    /// Removes a component. The GPU objects of the built-in components are
+    /// freed, so the returned ids no longer point to anything.
+    pub fn remove_component<T: 'static>(&mut self, id: EntityId) -> Option<T> {
+        let component = self.components.remove::<T>(id)?;
+        self.release_gpu(&component);
+        Some(component)
+    }
+
+    /// Frees the GPU objects of a component that left the world
+    fn release_gpu(&self, component: &dyn Any) {
+        if is_gpu_component(component) {
+            self.resource.borrow_mut().release(component);
+        }
+    }
+
+    /// `true` if the entity's `T` was added since the last change tick
+    pub fn is_added<T: 'static>(&self, id: EntityId) -> bool {
+        self.components.is_added::<T>(id)
+    }
+
+    /// `true` if the entity's `T` was added or mutably accessed since the last change tick
+    pub fn is_changed<T: 'static>(&self, id: EntityId) -> bool {
+        self.components.is_changed::<T>(id)
+    }
+
+    /// Entities that lost a `T`, by removal or despawn, since the last change tick
+    pub fn removed<T: 'static>(&self) -> RemovedComponents<'_, T> {
+        self.components.removed::<T>()
+    }
+
+    /// Ends the change detection frame, called by the [`App`](crate::App) runner
+    /// after rendering
+    pub fn clear_trackers(&mut self) {
+        self.components.clear_trackers();
+    }
+
+    /// Resizes the surface and sends a [`WindowResized`] event with its new size
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        let resized = self.resource.borrow().ctx.resize(size);
+        if let Some(size) = resized {
+            self.send_event(WindowResized { width: size.width, height: size.height });
+        }
+    }
+}
+
+
+
+
+
@@ -1,226 +1,355 @@
-use log::warn;
-use wgpu::{BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, PipelineLayoutDescriptor, PrimitiveTopology, RenderPipelineDescriptor, TextureFormat};
-use super::{bind_group, id, ComponentMesh, ComponentShaderMesh, ComponentShaderMeshUniform, ComponentUniform, Entity, Id, WebGPUType};
-
-
-pub trait SystemRenderPipelineMesh {
-    fn add_mesh_pipeline(&mut self, topology: PrimitiveTopology);
-}
-
-impl SystemRenderPipelineMesh for Entity<'_> {
-    fn add_mesh_pipeline(&mut self, topology: PrimitiveTopology) {
-        self.add_component(ComponentRenderPipelineMesh::new(self, topology));
-    }
-}
-
-pub struct ComponentRenderPipelineMesh {
-    pub id: Id
-}
-
-impl ComponentRenderPipelineMesh {
-    fn new(entity: &Entity, topology: PrimitiveTopology) -> Self {
-
-        let mut res = entity.game_resource.borrow_mut();
-
-        let mesh = entity.get_component::<ComponentMesh>().unwrap();
-        let shader = entity.get_component::<ComponentShaderMeshUniform>().unwrap();
-        let shader = &res.shader[&shader.id];
-
-            let pipeline = res.ctx.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: None,
-
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    compilation_options: Default::default(),
-                    buffers: &[mesh.vertex.layout()],
-                },
-
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    compilation_options: Default::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: TextureFormat::Rgba8UnormSrgb,
-                        blend: Some(wgpu::BlendState {
-                            color: wgpu::BlendComponent {
-                                operation: wgpu::BlendOperation::Add,
-                                src_factor: wgpu::BlendFactor::SrcAlpha,
-                                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            },
-                            alpha: wgpu::BlendComponent::REPLACE,
-                        }),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-
-                primitive: wgpu::PrimitiveState {
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    topology: PrimitiveTopology::TriangleList,
-                    ..Default::default()
-                },
-
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-                cache: None,
-            });
-
-        let id = id();
-        res.render_pipeline.insert(id, pipeline);
-
-        Self {
-            id
-        }
-    }
-}
-
-
-
-pub struct ComponentPipeLineLayout {
-    pipeline_layout: Id
-}
-
-
-pub trait SystemRenderPipelineMeshUniform {
-    fn add_mesh_uniform_pipeline(&mut self, topology: PrimitiveTopology);
-}
-
-impl SystemRenderPipelineMeshUniform for Entity<'_> {
-    fn add_mesh_uniform_pipeline(&mut self, topology: PrimitiveTopology) {
-        self.add_component(ComponentRenderPipelineMeshUniform::new(self, topology));
-    }
-}
-
-pub struct ComponentRenderPipelineMeshUniform {
-    pub id: Id,
-    pub bind_group: Id,
-}
-
-impl ComponentRenderPipelineMeshUniform {
-    fn new(entity: &Entity, topology: PrimitiveTopology) -> Self {
-
-        let mut res = entity.game_resource.borrow_mut();
-
-        let mesh = entity.get_component::<ComponentMesh>().unwrap();
-        let shader = entity.get_component::<ComponentShaderMeshUniform>().unwrap();
-        let uniform = entity.get_components::<ComponentUniform>().unwrap();
-
-        let mut entry = vec![];
-        let mut bind = 0;
-
-        for i in &uniform {
-            let s = res.uniform_buffer[&i.buffer].as_entire_binding();
-
-            entry.push(BindGroupLayoutEntry {
-                binding: bind,
-                visibility: i.visible,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None },
-                count: None,
-            });
-
-            bind += 1;
-        }
-
-        warn!("{:?}", entry);
-
-        let bind_group_layout = res.ctx.device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: None,
-            entries: &entry
-        });
-
-        let mut bind = 0;
-        let mut v = vec![];
-
-        for i in &uniform {
-
-            v.push(BindGroupEntry {
-                binding: bind,
-                resource: res.uniform_buffer[&i.buffer].as_entire_binding()
-            });
-
-            bind += 1;
-        }
-
-        warn!("{:?}", v);
-
-        let bind_group = res.ctx.device.create_bind_group(&BindGroupDescriptor {
-            label: None,
-            layout: &bind_group_layout,
-            entries: &v
-        });
-
-        let bind_group_id = id();
-        res.bind_group.insert(bind_group_id, bind_group);
-
-        let pipeline_layout = res.ctx.device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let shader = &res.shader[&shader.id];
-
-        let pipeline = res.ctx.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                compilation_options: Default::default(),
-                buffers: &[mesh.vertex.layout()],
-            },
-
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    compilation_options: Default::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: TextureFormat::Rgba8UnormSrgb,
-                        blend: Some(wgpu::BlendState {
-                            color: wgpu::BlendComponent {
-                                operation: wgpu::BlendOperation::Add,
-                                src_factor: wgpu::BlendFactor::SrcAlpha,
-                                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            },
-                            alpha: wgpu::BlendComponent::REPLACE,
-                        }),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-
-                primitive: wgpu::PrimitiveState {
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    topology,
-                    ..Default::default()
-                },
-
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-                cache: None,
-            });
-
-        let pipeline_id = id();
-        res.render_pipeline.insert(pipeline_id, pipeline);
-
-        Self {
-            id: pipeline_id,
-            bind_group: bind_group_id,
-        }
-    }
-}
-
-
-
-
-
+use log::warn;
+use wgpu::{BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, PipelineLayoutDescriptor, PrimitiveTopology, RenderPipeline, RenderPipelineDescriptor, ShaderModule, TextureFormat};
+use super::{bind_group, id, ComponentMesh, ComponentShaderMesh, ComponentShaderMeshInstanced, ComponentShaderMeshUniform, ComponentUniform, Entity, GameResource, Id, InstanceRaw, Vertex3D, WebGPUType, DEPTH_FORMAT};
+
+
+/// How a mesh pipeline writes its color, read from the entity when the
+/// pipeline is added. Blended meshes are drawn after the opaque ones,
+/// back to front.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    /// Blends with what is behind using the fragment alpha
+    Blend,
+}
+
+impl AlphaMode {
+    pub fn blend_state(&self) -> Option<wgpu::BlendState> {
+        match self {
+            AlphaMode::Opaque => None,
+            AlphaMode::Blend => Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    operation: wgpu::BlendOperation::Add,
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                },
+                alpha: wgpu::BlendComponent::REPLACE,
+            }),
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        *self == AlphaMode::Blend
+    }
+
+    /// Depth test of the mesh pipelines, blended meshes test against the
+    /// opaque ones but don't hide what is drawn after them
+    pub fn depth_stencil(&self) -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: !self.is_transparent(),
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+}
+
+pub trait SystemRenderPipelineMesh {
+    fn add_mesh_pipeline(&mut self, topology: PrimitiveTopology);
+}
+
+impl SystemRenderPipelineMesh for Entity<'_, '_> {
+    fn add_mesh_pipeline(&mut self, topology: PrimitiveTopology) {
+        self.add_component(ComponentRenderPipelineMesh::new(self, topology));
+    }
+}
+
+pub struct ComponentRenderPipelineMesh {
+    pub id:         Id,
+    pub alpha_mode: AlphaMode,
+    pub topology:   PrimitiveTopology,
+}
+
+impl ComponentRenderPipelineMesh {
+    fn new(entity: &Entity, topology: PrimitiveTopology) -> Self {
+
+        let mut res = entity.game_resource().borrow_mut();
+        let alpha_mode = entity.get_component::<AlphaMode>().copied().unwrap_or_default();
+
+        let mesh = entity.get_component::<ComponentMesh>().unwrap();
+        let shader = entity.get_component::<ComponentShaderMesh>().unwrap();
+        let shader = &res.shader[&shader.id];
+
+        let pipeline_layout = res.ctx.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&res.camera_bind_group_layout, &res.model_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+            let pipeline = res.ctx.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: Default::default(),
+                    buffers: &[mesh.vertex.layout()],
+                },
+
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: res.ctx.surface_format,
+                        blend: alpha_mode.blend_state(),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+
+                primitive: wgpu::PrimitiveState {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    topology,
+                    ..Default::default()
+                },
+
+                depth_stencil: Some(alpha_mode.depth_stencil()),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        let id = id();
+        res.render_pipeline.insert(id, pipeline);
+
+        Self {
+            id,
+            alpha_mode,
+            topology,
+        }
+    }
+}
+
+
+
+pub trait SystemRenderPipelineMeshInstanced {
+    fn add_mesh_instanced_pipeline(&mut self, topology: PrimitiveTopology);
+}
+
+impl SystemRenderPipelineMeshInstanced for Entity<'_, '_> {
+    fn add_mesh_instanced_pipeline(&mut self, topology: PrimitiveTopology) {
+        self.add_component(ComponentRenderPipelineMeshInstanced::new(self, topology));
+    }
+}
+
+/// Pipeline drawing the entity and every entity with an
+/// [`InstanceOf`](super::InstanceOf) pointing at it in one instanced draw
+pub struct ComponentRenderPipelineMeshInstanced {
+    pub id:         Id,
+    pub alpha_mode: AlphaMode,
+}
+
+impl ComponentRenderPipelineMeshInstanced {
+    fn new(entity: &Entity, topology: PrimitiveTopology) -> Self {
+
+        let mut res = entity.game_resource().borrow_mut();
+        let alpha_mode = entity.get_component::<AlphaMode>().copied().unwrap_or_default();
+
+        entity.get_component::<ComponentMesh>().expect("Error instanced pipeline needs a ComponentMesh");
+        let shader = entity.get_component::<ComponentShaderMeshInstanced>().expect("Error instanced pipeline needs a ComponentShaderMeshInstanced");
+
+        let pipeline = mesh_instanced_pipeline(&res, &res.shader[&shader.id], alpha_mode, topology);
+
+        let id = id();
+        res.render_pipeline.insert(id, pipeline);
+
+        Self {
+            id,
+            alpha_mode,
+        }
+    }
+}
+
+/// Pipeline of `mesh_instanced.wgsl` over [`Vertex3D`] meshes, also used by
+/// the [`MeshBatches`](super::MeshBatches)
+pub(crate) fn mesh_instanced_pipeline(res: &GameResource, shader: &ShaderModule, alpha_mode: AlphaMode, topology: PrimitiveTopology) -> RenderPipeline {
+    let pipeline_layout = res.ctx.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("Mesh Instanced Pipeline Layout"),
+        bind_group_layouts: &[&res.camera_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    res.ctx.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Mesh Instanced Pipeline"),
+        layout: Some(&pipeline_layout),
+
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: Default::default(),
+            buffers: &[Vec::<Vertex3D>::new().layout(), Vec::<InstanceRaw>::new().layout()],
+        },
+
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: res.ctx.surface_format,
+                blend: alpha_mode.blend_state(),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+
+        primitive: wgpu::PrimitiveState {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            topology,
+            ..Default::default()
+        },
+
+        depth_stencil: Some(alpha_mode.depth_stencil()),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+
+
+pub struct ComponentPipeLineLayout {
+    pipeline_layout: Id
+}
+
+
+pub trait SystemRenderPipelineMeshUniform {
+    fn add_mesh_uniform_pipeline(&mut self, topology: PrimitiveTopology);
+}
+
+impl SystemRenderPipelineMeshUniform for Entity<'_, '_> {
+    fn add_mesh_uniform_pipeline(&mut self, topology: PrimitiveTopology) {
+        self.add_component(ComponentRenderPipelineMeshUniform::new(self, topology));
+    }
+}
+
+pub struct ComponentRenderPipelineMeshUniform {
+    pub id:         Id,
+    pub bind_group: Id,
+    pub alpha_mode: AlphaMode,
+}
+
+impl ComponentRenderPipelineMeshUniform {
+    fn new(entity: &Entity, topology: PrimitiveTopology) -> Self {
+
+        let mut res = entity.game_resource().borrow_mut();
+        let alpha_mode = entity.get_component::<AlphaMode>().copied().unwrap_or_default();
+
+        let mesh = entity.get_component::<ComponentMesh>().unwrap();
+        let shader = entity.get_component::<ComponentShaderMeshUniform>().unwrap();
+        let uniform = &entity.get_component::<ComponentUniform>().unwrap().uniforms;
+
+        let mut entry = vec![];
+        let mut bind = 0;
+
+        for i in uniform {
+            let s = res.uniform_buffer[&i.buffer].as_entire_binding();
+
+            entry.push(BindGroupLayoutEntry {
+                binding: bind,
+                visibility: i.visible,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None },
+                count: None,
+            });
+
+            bind += 1;
+        }
+
+        warn!("{:?}", entry);
+
+        let bind_group_layout = res.ctx.device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &entry
+        });
+
+        let mut bind = 0;
+        let mut v = vec![];
+
+        for i in uniform {
+
+            v.push(BindGroupEntry {
+                binding: bind,
+                resource: res.uniform_buffer[&i.buffer].as_entire_binding()
+            });
+
+            bind += 1;
+        }
+
+        warn!("{:?}", v);
+
+        let bind_group = res.ctx.device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &v
+        });
+
+        let bind_group_id = id();
+        res.bind_group.insert(bind_group_id, bind_group);
+
+        let pipeline_layout = res.ctx.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&res.camera_bind_group_layout, &res.model_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = &res.shader[&shader.id];
+
+        let pipeline = res.ctx.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[mesh.vertex.layout()],
+            },
+
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: res.ctx.surface_format,
+                        blend: alpha_mode.blend_state(),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+
+                primitive: wgpu::PrimitiveState {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    topology,
+                    ..Default::default()
+                },
+
+                depth_stencil: Some(alpha_mode.depth_stencil()),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        let pipeline_id = id();
+        res.render_pipeline.insert(pipeline_id, pipeline);
+
+        Self {
+            id: pipeline_id,
+            bind_group: bind_group_id,
+            alpha_mode,
+        }
+    }
+}
+
+
+
+
+
@@ -0,0 +1,551 @@
+use std::any::{type_name, TypeId};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use super::{is_newer_tick, Column, Components, Entities, EntityId, GameWorld};
+
+/// Component and resource types read and written by a query or a system.
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+    reads:              Vec<(TypeId, &'static str)>,
+    writes:             Vec<(TypeId, &'static str)>,
+    resource_reads:     Vec<(TypeId, &'static str)>,
+    resource_writes:    Vec<(TypeId, &'static str)>,
+}
+
+impl Access {
+
+    pub fn add_read<T: 'static>(&mut self) {
+        self.reads.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    pub fn add_write<T: 'static>(&mut self) {
+        self.writes.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    pub fn add_resource_read<T: 'static>(&mut self) {
+        self.resource_reads.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    pub fn add_resource_write<T: 'static>(&mut self) {
+        self.resource_writes.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    pub fn extend(&mut self, other: &Access) {
+        self.reads.extend_from_slice(&other.reads);
+        self.writes.extend_from_slice(&other.writes);
+        self.resource_reads.extend_from_slice(&other.resource_reads);
+        self.resource_writes.extend_from_slice(&other.resource_writes);
+    }
+
+    pub fn reads(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.reads.iter().map(|(_, name)| *name)
+    }
+
+    pub fn writes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.writes.iter().map(|(_, name)| *name)
+    }
+
+    pub fn resource_reads(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.resource_reads.iter().map(|(_, name)| *name)
+    }
+
+    pub fn resource_writes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.resource_writes.iter().map(|(_, name)| *name)
+    }
+
+    /// Names of the component and resource types one side writes while the
+    /// other reads or writes them
+    pub fn conflicts(&self, other: &Access) -> Vec<&'static str> {
+        let mut conflicts = vec![];
+        collect_conflicts(&mut conflicts, (&self.reads, &self.writes), (&other.reads, &other.writes));
+        collect_conflicts(
+            &mut conflicts,
+            (&self.resource_reads, &self.resource_writes),
+            (&other.resource_reads, &other.resource_writes),
+        );
+        conflicts
+    }
+
+    /// Types written more than once, or both read and written, within this access
+    fn aliased(&self) -> Option<&'static str> {
+        for (i, (id, name)) in self.writes.iter().enumerate() {
+            if self.writes[i + 1..].iter().chain(&self.reads).any(|(o, _)| o == id) {
+                return Some(name);
+            }
+        }
+        None
+    }
+}
+
+type AccessList = [(TypeId, &'static str)];
+
+fn collect_conflicts(
+    conflicts: &mut Vec<&'static str>,
+    (reads, writes): (&AccessList, &AccessList),
+    (other_reads, other_writes): (&AccessList, &AccessList),
+) {
+    for (id, name) in writes {
+        if other_writes.iter().chain(other_reads).any(|(o, _)| o == id) && !conflicts.contains(name) {
+            conflicts.push(*name);
+        }
+    }
+
+    for (id, name) in other_writes {
+        if reads.iter().any(|(o, _)| o == id) && !conflicts.contains(name) {
+            conflicts.push(*name);
+        }
+    }
+}
+
+/// Something that can be fetched for one entity by a query: `&T`, `&mut T`,
+/// `Option<Q>`, [`EntityId`] or a tuple of those.
+///
+/// # Safety
+///
+/// `update_access` must report every column `fetch` reads or writes, the
+/// query uses it to reject aliasing mutable access.
+pub unsafe trait WorldQuery {
+    type Item<'w>;
+    type State;
+
+    /// `None` when no entity can match, e.g. the column was never created
+    ///
+    /// # Safety
+    ///
+    /// `components` must be valid, and exclusively borrowed if `Self` writes.
+    unsafe fn init_state(components: *mut Components) -> Option<Self::State>;
+
+    /// # Safety
+    ///
+    /// The state must come from a live `Components` that is not accessed
+    /// elsewhere in a conflicting way for `'w`.
+    unsafe fn fetch<'w>(state: &Self::State, entity: EntityId) -> Option<Self::Item<'w>>;
+
+    /// Entities of the smallest column `fetch` requires, `None` when it
+    /// requires none, e.g. [`EntityId`] or `Option<Q>`
+    ///
+    /// # Safety
+    ///
+    /// Same as [`fetch`](Self::fetch).
+    unsafe fn entities(state: &Self::State) -> Option<*const [EntityId]>;
+
+    fn update_access(access: &mut Access);
+}
+
+/// Marker for queries that never hand out mutable references.
+///
+/// # Safety
+///
+/// Implementors must only read.
+pub unsafe trait ReadOnlyWorldQuery: WorldQuery {}
+
+unsafe impl<T: 'static> WorldQuery for &T {
+    type Item<'w> = &'w T;
+    type State = *const Column<T>;
+
+    unsafe fn init_state(components: *mut Components) -> Option<Self::State> {
+        (*components).column::<T>().map(|c| c as *const Column<T>)
+    }
+
+    unsafe fn fetch<'w>(state: &Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+        (**state).get(entity)
+    }
+
+    unsafe fn entities(state: &Self::State) -> Option<*const [EntityId]> {
+        Some((**state).entities())
+    }
+
+    fn update_access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+}
+
+unsafe impl<T: 'static> ReadOnlyWorldQuery for &T {}
+
+/// Mutable access to a component fetched by a `&mut T` query, marks the
+/// component as changed when dereferenced mutably.
+pub struct Mut<'w, T> {
+    value:      &'w mut T,
+    changed:    &'w mut u32,
+    tick:       u32,
+}
+
+impl<'w, T> Mut<'w, T> {
+
+    /// Marks the component as changed without writing it
+    pub fn set_changed(&mut self) {
+        *self.changed = self.tick;
+    }
+
+    /// Writes without marking the component as changed
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        self.value
+    }
+
+    pub fn into_inner(self) -> &'w mut T {
+        *self.changed = self.tick;
+        self.value
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.set_changed();
+        self.value
+    }
+}
+
+unsafe impl<T: 'static> WorldQuery for &mut T {
+    type Item<'w> = Mut<'w, T>;
+    /// The column, only read to find slots, its values and changed ticks
+    /// and the change tick
+    type State = (*const Column<T>, *mut T, *mut u32, u32);
+
+    unsafe fn init_state(components: *mut Components) -> Option<Self::State> {
+        let tick = (*components).change_tick();
+        let column = (*components).column_mut::<T>()?;
+        let (dense, changed) = column.as_mut_ptrs();
+        Some((column as *const Column<T>, dense, changed, tick))
+    }
+
+    unsafe fn fetch<'w>(state: &Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+        // The items handed out earlier are still alive, so only the one slot
+        // is borrowed mutably and never the whole column
+        let slot = (*state.0).slot(entity)?;
+        Some(Mut { value: &mut *state.1.add(slot), changed: &mut *state.2.add(slot), tick: state.3 })
+    }
+
+    unsafe fn entities(state: &Self::State) -> Option<*const [EntityId]> {
+        Some((*state.0).entities())
+    }
+
+    fn update_access(access: &mut Access) {
+        access.add_write::<T>();
+    }
+}
+
+unsafe impl<Q: WorldQuery> WorldQuery for Option<Q> {
+    type Item<'w> = Option<Q::Item<'w>>;
+    type State = Option<Q::State>;
+
+    unsafe fn init_state(components: *mut Components) -> Option<Self::State> {
+        Some(Q::init_state(components))
+    }
+
+    unsafe fn fetch<'w>(state: &Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+        Some(state.as_ref().and_then(|s| Q::fetch(s, entity)))
+    }
+
+    unsafe fn entities(_: &Self::State) -> Option<*const [EntityId]> {
+        None
+    }
+
+    fn update_access(access: &mut Access) {
+        Q::update_access(access);
+    }
+}
+
+unsafe impl<Q: ReadOnlyWorldQuery> ReadOnlyWorldQuery for Option<Q> {}
+
+unsafe impl WorldQuery for EntityId {
+    type Item<'w> = EntityId;
+    type State = ();
+
+    unsafe fn init_state(_: *mut Components) -> Option<Self::State> {
+        Some(())
+    }
+
+    unsafe fn fetch<'w>(_: &Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+        Some(entity)
+    }
+
+    unsafe fn entities(_: &Self::State) -> Option<*const [EntityId]> {
+        None
+    }
+
+    fn update_access(_: &mut Access) {}
+}
+
+unsafe impl ReadOnlyWorldQuery for EntityId {}
+
+macro_rules! impl_world_query_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        unsafe impl<$($name: WorldQuery),*> WorldQuery for ($($name,)*) {
+            type Item<'w> = ($($name::Item<'w>,)*);
+            type State = ($($name::State,)*);
+
+            unsafe fn init_state(components: *mut Components) -> Option<Self::State> {
+                Some(($($name::init_state(components)?,)*))
+            }
+
+            unsafe fn fetch<'w>(state: &Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+                let ($($name,)*) = state;
+                Some(($($name::fetch($name, entity)?,)*))
+            }
+
+            unsafe fn entities(state: &Self::State) -> Option<*const [EntityId]> {
+                let ($($name,)*) = state;
+                let mut smallest: Option<*const [EntityId]> = None;
+                $(
+                    if let Some(entities) = $name::entities($name) {
+                        if smallest.is_none_or(|s| entities.len() < s.len()) {
+                            smallest = Some(entities);
+                        }
+                    }
+                )*
+                smallest
+            }
+
+            fn update_access(access: &mut Access) {
+                $($name::update_access(access);)*
+            }
+        }
+
+        unsafe impl<$($name: ReadOnlyWorldQuery),*> ReadOnlyWorldQuery for ($($name,)*) {}
+    };
+}
+
+impl_world_query_tuple!(A);
+impl_world_query_tuple!(A, B);
+impl_world_query_tuple!(A, B, C);
+impl_world_query_tuple!(A, B, C, D);
+impl_world_query_tuple!(A, B, C, D, E);
+impl_world_query_tuple!(A, B, C, D, E, F);
+impl_world_query_tuple!(A, B, C, D, E, F, G);
+impl_world_query_tuple!(A, B, C, D, E, F, G, H);
+
+/// Narrows a query without fetching anything: [`With`], [`Without`],
+/// [`Added`], [`Changed`] or a tuple of those.
+pub trait QueryFilter {
+    type State;
+
+    fn init_state(components: &Components) -> Self::State;
+    fn matches(state: &Self::State, entity: EntityId) -> bool;
+}
+
+/// Only entities that have a `T`
+pub struct With<T>(PhantomData<T>);
+
+/// Only entities that don't have a `T`
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: 'static> QueryFilter for With<T> {
+    type State = Option<*const Column<T>>;
+
+    fn init_state(components: &Components) -> Self::State {
+        components.column::<T>().map(|c| c as *const Column<T>)
+    }
+
+    fn matches(state: &Self::State, entity: EntityId) -> bool {
+        state.is_some_and(|c| unsafe { (*c).contains(entity) })
+    }
+}
+
+impl<T: 'static> QueryFilter for Without<T> {
+    type State = Option<*const Column<T>>;
+
+    fn init_state(components: &Components) -> Self::State {
+        components.column::<T>().map(|c| c as *const Column<T>)
+    }
+
+    fn matches(state: &Self::State, entity: EntityId) -> bool {
+        state.is_none_or(|c| unsafe { !(*c).contains(entity) })
+    }
+}
+
+/// Only entities whose `T` was added since the last change tick
+pub struct Added<T>(PhantomData<T>);
+
+/// Only entities whose `T` was added or mutably accessed since the last change tick
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: 'static> QueryFilter for Added<T> {
+    type State = Option<(*const Column<T>, u32, u32)>;
+
+    fn init_state(components: &Components) -> Self::State {
+        let (last, current) = (components.last_change_tick(), components.change_tick());
+        components.column::<T>().map(|c| (c as *const Column<T>, last, current))
+    }
+
+    fn matches(state: &Self::State, entity: EntityId) -> bool {
+        state.is_some_and(|(c, last, current)| unsafe { (*c).added_tick(entity) }.is_some_and(|t| is_newer_tick(t, last, current)))
+    }
+}
+
+impl<T: 'static> QueryFilter for Changed<T> {
+    type State = Option<(*const Column<T>, u32, u32)>;
+
+    fn init_state(components: &Components) -> Self::State {
+        let (last, current) = (components.last_change_tick(), components.change_tick());
+        components.column::<T>().map(|c| (c as *const Column<T>, last, current))
+    }
+
+    fn matches(state: &Self::State, entity: EntityId) -> bool {
+        state.is_some_and(|(c, last, current)| unsafe { (*c).changed_tick(entity) }.is_some_and(|t| is_newer_tick(t, last, current)))
+    }
+}
+
+impl QueryFilter for () {
+    type State = ();
+
+    fn init_state(_: &Components) -> Self::State {}
+
+    fn matches(_: &Self::State, _: EntityId) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_query_filter_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            type State = ($($name::State,)*);
+
+            fn init_state(components: &Components) -> Self::State {
+                ($($name::init_state(components),)*)
+            }
+
+            fn matches(state: &Self::State, entity: EntityId) -> bool {
+                let ($($name,)*) = state;
+                $($name::matches($name, entity))&&*
+            }
+        }
+    };
+}
+
+impl_query_filter_tuple!(A);
+impl_query_filter_tuple!(A, B);
+impl_query_filter_tuple!(A, B, C);
+impl_query_filter_tuple!(A, B, C, D);
+
+/// Entities a [`QueryIter`] visits. Neither can change while the world is
+/// borrowed by the query.
+enum Candidates {
+    /// Entities of the smallest column the query requires
+    Column(*const [EntityId]),
+    /// Every alive entity, for queries that require no column
+    All(*const Entities),
+}
+
+impl Candidates {
+
+    unsafe fn len(&self) -> usize {
+        match *self {
+            Candidates::Column(entities) => entities.len(),
+            Candidates::All(entities) => (*entities).indices(),
+        }
+    }
+
+    /// `None` for a dead index of [`Candidates::All`]
+    unsafe fn get(&self, index: usize) -> Option<EntityId> {
+        match *self {
+            Candidates::Column(entities) => Some((*entities)[index]),
+            Candidates::All(entities) => (*entities).at(index),
+        }
+    }
+}
+
+/// Iterator over the entities matching `Q` and `F`.
+pub struct QueryIter<'w, Q: WorldQuery, F: QueryFilter = ()> {
+    state:      Option<Q::State>,
+    filter:     F::State,
+    entities:   Candidates,
+    next:       usize,
+    marker:     PhantomData<&'w mut Components>,
+}
+
+impl<'w, Q: WorldQuery, F: QueryFilter> QueryIter<'w, Q, F> {
+
+    /// # Safety
+    ///
+    /// The caller must hold a borrow of the world matching `Q` for `'w`:
+    /// shared for read-only queries, exclusive otherwise.
+    unsafe fn new(world: *mut GameWorld<'_>) -> Self {
+        let mut access = Access::default();
+        Q::update_access(&mut access);
+        if let Some(name) = access.aliased() {
+            panic!("Query accesses {} mutably more than once", name);
+        }
+
+        // No reference is created here, read-only queries come from a shared borrow
+        let components: *mut Components = std::ptr::addr_of_mut!((*world).components);
+        let state = Q::init_state(components);
+        let filter = F::init_state(&*components);
+
+        let entities = match state.as_ref().map(|s| Q::entities(s)) {
+            Some(Some(entities)) => Candidates::Column(entities),
+            Some(None) => Candidates::All(std::ptr::addr_of!((*world).entities)),
+            None => Candidates::Column(&[]),
+        };
+
+        Self {
+            state,
+            filter,
+            entities,
+            next: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'w, Q: WorldQuery, F: QueryFilter> Iterator for QueryIter<'w, Q, F> {
+    type Item = Q::Item<'w>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let state = self.state.as_ref()?;
+
+        while self.next < unsafe { self.entities.len() } {
+            let entity = unsafe { self.entities.get(self.next) };
+            self.next += 1;
+
+            let Some(entity) = entity else { continue };
+            if !F::matches(&self.filter, entity) {
+                continue;
+            }
+            if let Some(item) = unsafe { Q::fetch(state, entity) } {
+                return Some(item);
+            }
+        }
+
+        None
+    }
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Iterates every entity that has all the components of `Q`.
+    ///
+    /// ```ignore
+    /// for (mesh, pipeline) in world.query::<(&ComponentMesh, &ComponentRenderPipelineMesh)>() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn query<Q: ReadOnlyWorldQuery>(&self) -> QueryIter<'_, Q> {
+        unsafe { QueryIter::new(self as *const _ as *mut GameWorld) }
+    }
+
+    /// Like [`query`](Self::query), narrowed by a filter such as `(With<A>, Without<B>)`
+    pub fn query_filtered<Q: ReadOnlyWorldQuery, F: QueryFilter>(&self) -> QueryIter<'_, Q, F> {
+        unsafe { QueryIter::new(self as *const _ as *mut GameWorld) }
+    }
+
+    /// Iterates with mutable access, e.g. `world.query_mut::<(&mut A, &B)>()`.
+    /// Panics if the same component is requested mutably twice.
+    pub fn query_mut<Q: WorldQuery>(&mut self) -> QueryIter<'_, Q> {
+        unsafe { QueryIter::new(self) }
+    }
+
+    pub fn query_filtered_mut<Q: WorldQuery, F: QueryFilter>(&mut self) -> QueryIter<'_, Q, F> {
+        unsafe { QueryIter::new(self) }
+    }
+}
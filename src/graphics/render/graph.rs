@@ -0,0 +1,510 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::iter;
+
+use log::warn;
+use wgpu::{CommandEncoder, CommandEncoderDescriptor, Device, Extent3d, SurfaceError, SurfaceTexture, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor};
+use winit::dpi::PhysicalSize;
+
+use super::super::{GameResource, GameWorld, WebGPUContext};
+
+/// Name of the surface texture, acquired and presented by the graph
+pub const SURFACE: &str = "surface";
+
+/// Size of a transient render target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetSize {
+    /// The surface size times a factor, e.g. `0.5` for a half resolution bloom target
+    Surface(f32),
+    Fixed(u32, u32),
+}
+
+/// Format of [`TargetDesc::depth`] targets and of the mesh pipelines' depth
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// Transient texture allocated by the [`RenderGraph`] for the frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetDesc {
+    /// `None` uses the surface format, so the built-in pipelines can draw into it
+    pub format: Option<TextureFormat>,
+    pub size:   TargetSize,
+    pub usage:  TextureUsages,
+}
+
+impl TargetDesc {
+
+    /// Surface sized color target that later passes can sample
+    pub fn color() -> Self {
+        Self {
+            format: None,
+            size:   TargetSize::Surface(1.0),
+            usage:  TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        }
+    }
+
+    /// Surface sized [`DEPTH_FORMAT`] target
+    pub fn depth() -> Self {
+        Self {
+            format: Some(DEPTH_FORMAT),
+            size:   TargetSize::Surface(1.0),
+            usage:  TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        }
+    }
+
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn with_size(mut self, size: TargetSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_usage(mut self, usage: TextureUsages) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    fn resolve(&self, surface_format: TextureFormat, surface: PhysicalSize<u32>) -> Resolved {
+        let (width, height) = match self.size {
+            TargetSize::Surface(scale) => (
+                ((surface.width as f32 * scale) as u32).max(1),
+                ((surface.height as f32 * scale) as u32).max(1),
+            ),
+            TargetSize::Fixed(width, height) => (width, height),
+        };
+
+        Resolved {
+            format: self.format.unwrap_or(surface_format),
+            width,
+            height,
+            usage:  self.usage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Resolved {
+    format: TextureFormat,
+    width:  u32,
+    height: u32,
+    usage:  TextureUsages,
+}
+
+type NodeFn = Box<dyn Fn(&mut RenderContext)>;
+
+/// A pass of the [`RenderGraph`] with the named textures and buffers it
+/// reads and writes.
+///
+/// ```ignore
+/// graph.add_target("scene", TargetDesc::color());
+/// graph.add_node(RenderNode::new("post", |ctx| {
+///     let scene = ctx.view("scene");
+///     let surface = ctx.view(SURFACE);
+///     // ... ctx.encoder.begin_render_pass(...)
+/// }).reads("scene").writes(SURFACE));
+/// ```
+pub struct RenderNode {
+    name:   String,
+    reads:  Vec<String>,
+    writes: Vec<String>,
+    run:    NodeFn,
+}
+
+impl RenderNode {
+
+    pub fn new(name: &str, run: impl Fn(&mut RenderContext) + 'static) -> Self {
+        Self {
+            name:   name.to_string(),
+            reads:  vec![],
+            writes: vec![],
+            run:    Box::new(run),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Runs after every node writing `resource`
+    pub fn reads(mut self, resource: &str) -> Self {
+        self.reads.push(resource.to_string());
+        self
+    }
+
+    /// Runs after earlier writers of `resource` and before its readers
+    pub fn writes(mut self, resource: &str) -> Self {
+        self.writes.push(resource.to_string());
+        self
+    }
+
+    fn uses(&self, resource: &str) -> bool {
+        self.reads.iter().chain(&self.writes).any(|r| r == resource)
+    }
+}
+
+/// What a node gets while it records: the world, the GPU resources, the
+/// frame's command encoder and the render targets it declared.
+pub struct RenderContext<'a, 'p> {
+    pub world:      &'a GameWorld<'p>,
+    pub res:        &'a GameResource<'p>,
+    pub encoder:    &'a mut CommandEncoder,
+    node:           &'a RenderNode,
+    targets:        &'a HashMap<String, (TextureView, PhysicalSize<u32>)>,
+}
+
+impl<'a, 'p> RenderContext<'a, 'p> {
+
+    /// View of a target or of the [`SURFACE`]. Panics if the node did not
+    /// declare it, or if it is not a target of the graph.
+    pub fn view(&self, name: &str) -> &'a TextureView {
+        &self.target(name).0
+    }
+
+    pub fn size(&self, name: &str) -> PhysicalSize<u32> {
+        self.target(name).1
+    }
+
+    fn target(&self, name: &str) -> &'a (TextureView, PhysicalSize<u32>) {
+        if !self.node.uses(name) {
+            panic!("Render node `{}` uses `{}` without declaring it", self.node.name, name);
+        }
+        let targets = self.targets;
+        targets
+            .get(name)
+            .unwrap_or_else(|| panic!("Render node `{}` uses unknown target `{}`", self.node.name, name))
+    }
+}
+
+/// Passes of a frame, ordered by the resources they read and write and
+/// recorded into one command encoder. wgpu inserts the barriers between a
+/// pass writing a target and a pass sampling it.
+///
+/// Transient targets are allocated for the frame. Targets with the same
+/// format, size and usage share a texture when their lifetimes don't overlap,
+/// textures are kept between frames while they still fit.
+#[derive(Default)]
+pub struct RenderGraph {
+    nodes:      Vec<RenderNode>,
+    targets:    HashMap<String, TargetDesc>,
+    order:      RefCell<Option<Vec<usize>>>,
+    pool:       RefCell<Vec<(Resolved, Texture)>>,
+}
+
+impl RenderGraph {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node, replacing a node of the same name
+    pub fn add_node(&mut self, node: RenderNode) {
+        self.nodes.retain(|n| n.name != node.name);
+        self.nodes.push(node);
+        self.order.replace(None);
+    }
+
+    pub fn remove_node(&mut self, name: &str) -> Option<RenderNode> {
+        let index = self.nodes.iter().position(|n| n.name == name)?;
+        self.order.replace(None);
+        Some(self.nodes.remove(index))
+    }
+
+    pub fn has_node(&self, name: &str) -> bool {
+        self.nodes.iter().any(|n| n.name == name)
+    }
+
+    /// Declares a transient texture. Names that are neither a target nor
+    /// the [`SURFACE`] only order the nodes, e.g. buffers owned elsewhere.
+    pub fn add_target(&mut self, name: &str, desc: TargetDesc) {
+        self.targets.insert(name.to_string(), desc);
+    }
+
+    /// Node names in the order they record.
+    /// Panics if the reads and writes form a cycle.
+    pub fn node_order(&self) -> Vec<String> {
+        self.order().iter().map(|&i| self.nodes[i].name.clone()).collect()
+    }
+
+    /// `edges[a]` holds the nodes that must run after `a`
+    fn edges(&self) -> Vec<Vec<usize>> {
+        let mut edges = vec![vec![]; self.nodes.len()];
+        let mut resources: Vec<&str> = vec![];
+        for node in &self.nodes {
+            for r in node.reads.iter().chain(&node.writes) {
+                if !resources.contains(&r.as_str()) {
+                    resources.push(r);
+                }
+            }
+        }
+
+        for resource in resources {
+            let writers: Vec<usize> = (0..self.nodes.len())
+                .filter(|&i| self.nodes[i].writes.iter().any(|w| w == resource))
+                .collect();
+
+            if writers.is_empty() && resource != SURFACE {
+                warn!("Render resource `{}` is read but never written", resource);
+            }
+
+            // Writers run in insertion order, readers after all of them
+            for pair in writers.windows(2) {
+                edges[pair[0]].push(pair[1]);
+            }
+            for (i, node) in self.nodes.iter().enumerate() {
+                if writers.contains(&i) || !node.reads.iter().any(|r| r == resource) {
+                    continue;
+                }
+                for &w in &writers {
+                    edges[w].push(i);
+                }
+            }
+        }
+
+        edges
+    }
+
+    fn order(&self) -> Vec<usize> {
+        if let Some(order) = self.order.borrow().as_ref() {
+            return order.clone();
+        }
+
+        let edges = self.edges();
+        let mut incoming = vec![0; self.nodes.len()];
+        for targets in &edges {
+            for &t in targets {
+                incoming[t] += 1;
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut done = vec![false; self.nodes.len()];
+
+        while order.len() < self.nodes.len() {
+            let next = (0..self.nodes.len())
+                .find(|&i| !done[i] && incoming[i] == 0)
+                .unwrap_or_else(|| {
+                    let cycle: Vec<&str> = (0..self.nodes.len())
+                        .filter(|&i| !done[i])
+                        .map(|i| self.nodes[i].name.as_str())
+                        .collect();
+                    panic!("Render nodes have a cyclic dependency: {:?}", cycle)
+                });
+
+            done[next] = true;
+            order.push(next);
+            for &t in &edges[next] {
+                incoming[t] -= 1;
+            }
+        }
+
+        self.order.replace(Some(order.clone()));
+        order
+    }
+
+    /// Index in `pool` of the texture of every used target. Targets whose
+    /// first and last use don't overlap share a texture.
+    fn alias(&self, order: &[usize], resolved: &HashMap<&str, Resolved>, device: &Device) -> HashMap<String, usize> {
+        // (first, last) position in `order` of every used target
+        let mut lifetimes: Vec<(&str, usize, usize)> = vec![];
+        for (position, &node) in order.iter().enumerate() {
+            let node = &self.nodes[node];
+            for name in node.reads.iter().chain(&node.writes) {
+                if !resolved.contains_key(name.as_str()) {
+                    continue;
+                }
+                match lifetimes.iter_mut().find(|(n, _, _)| n == name) {
+                    Some(lifetime) => lifetime.2 = position,
+                    None => lifetimes.push((name, position, position)),
+                }
+            }
+        }
+
+        let mut pool = self.pool.borrow_mut();
+        let mut busy_until: Vec<Option<usize>> = vec![None; pool.len()];
+        let mut assigned = HashMap::new();
+
+        for (name, first, last) in lifetimes {
+            let desc = resolved[name];
+            let free = (0..pool.len()).find(|&i| pool[i].0 == desc && busy_until[i].is_none_or(|end| end < first));
+
+            let index = free.unwrap_or_else(|| {
+                let texture = device.create_texture(&TextureDescriptor {
+                    label: Some(name),
+                    size: Extent3d { width: desc.width, height: desc.height, depth_or_array_layers: 1 },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: desc.format,
+                    usage: desc.usage,
+                    view_formats: &[],
+                });
+                pool.push((desc, texture));
+                busy_until.push(None);
+                pool.len() - 1
+            });
+
+            busy_until[index] = Some(last);
+            assigned.insert(name.to_string(), index);
+        }
+
+        // Textures nobody used this frame, e.g. after a resize, are dropped
+        let mut remap = vec![None; pool.len()];
+        let mut kept = 0;
+        for (i, busy) in busy_until.iter().enumerate() {
+            if busy.is_some() {
+                remap[i] = Some(kept);
+                kept += 1;
+            }
+        }
+        let mut i = 0;
+        pool.retain(|_| {
+            i += 1;
+            busy_until[i - 1].is_some()
+        });
+
+        assigned.into_iter().map(|(name, index)| (name, remap[index].unwrap())).collect()
+    }
+
+    /// Runs every node into one command encoder, then submits it and
+    /// presents the surface if a node used it
+    pub fn execute(&self, world: &GameWorld) {
+        let res = world.resource.borrow();
+        if !res.ctx.resized.get() { return; }
+
+        // Acquired first, a lost surface is reconfigured before the targets are sized
+        let uses_surface = self.nodes.iter().any(|n| n.uses(SURFACE));
+        let output = match uses_surface {
+            true => match acquire_surface(&res.ctx) {
+                Some(output) => Some(output),
+                None => return,
+            },
+            false => None,
+        };
+
+        let order = self.order();
+        let surface_size = res.ctx.size.get();
+
+        let resolved: HashMap<&str, Resolved> = self.targets
+            .iter()
+            .map(|(name, desc)| (name.as_str(), desc.resolve(res.ctx.surface_format, surface_size)))
+            .collect();
+
+        let assigned = self.alias(&order, &resolved, &res.ctx.device);
+        let mut targets = HashMap::new();
+        {
+            let pool = self.pool.borrow();
+            for (name, index) in assigned {
+                let (desc, texture) = &pool[index];
+                let view = texture.create_view(&TextureViewDescriptor::default());
+                targets.insert(name, (view, PhysicalSize::new(desc.width, desc.height)));
+            }
+        }
+
+        if let Some(output) = &output {
+            let view = output.texture.create_view(&TextureViewDescriptor::default());
+            targets.insert(SURFACE.to_string(), (view, surface_size));
+        }
+
+        let mut encoder = res.ctx.device.create_command_encoder(&CommandEncoderDescriptor { label: Some("Render Graph Encoder") });
+
+        for &node in &order {
+            let node = &self.nodes[node];
+            let mut ctx = RenderContext {
+                world,
+                res: &res,
+                encoder: &mut encoder,
+                node,
+                targets: &targets,
+            };
+            (node.run)(&mut ctx);
+        }
+
+        res.ctx.queue.submit(iter::once(encoder.finish()));
+        if let Some(output) = output {
+            output.present();
+        }
+    }
+}
+
+/// Texture of the surface for this frame. A lost or outdated surface is
+/// configured again at the current size, `None` skips the frame.
+fn acquire_surface(ctx: &WebGPUContext) -> Option<SurfaceTexture> {
+    match ctx.surface.get_current_texture() {
+        Ok(output) => Some(output),
+        Err(SurfaceError::Timeout) => {
+            warn!("Error get surface texture: timeout, frame skipped");
+            None
+        }
+        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+            ctx.reconfigure();
+            match ctx.surface.get_current_texture() {
+                Ok(output) => Some(output),
+                Err(e) => {
+                    warn!("Error get surface texture after reconfigure: {}", e);
+                    None
+                }
+            }
+        }
+        Err(SurfaceError::OutOfMemory) => panic!("Error get surface texture: out of memory"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, reads: &[&str], writes: &[&str]) -> RenderNode {
+        let node = reads.iter().fold(RenderNode::new(name, |_| ()), |node, r| node.reads(r));
+        writes.iter().fold(node, |node, w| node.writes(w))
+    }
+
+    #[test]
+    fn readers_run_after_writers() {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node("post", &["scene"], &[SURFACE]));
+        graph.add_node(node("scene", &[], &["scene"]));
+
+        assert_eq!(graph.node_order(), ["scene", "post"]);
+    }
+
+    #[test]
+    fn writers_run_in_insertion_order_before_readers() {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node("ui", &["color"], &[SURFACE]));
+        graph.add_node(node("opaque", &[], &["color"]));
+        graph.add_node(node("transparent", &[], &["color"]));
+        graph.add_node(node("shadows", &[], &["shadow"]));
+        graph.add_node(node("lit", &["shadow"], &["color"]));
+
+        let order = graph.node_order();
+        let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+
+        assert!(position("opaque") < position("transparent"));
+        assert!(position("transparent") < position("lit"));
+        assert!(position("shadows") < position("lit"));
+        assert_eq!(position("ui"), order.len() - 1);
+    }
+
+    #[test]
+    fn replaced_and_removed_nodes_reorder() {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node("a", &["b"], &[]));
+        graph.add_node(node("b", &[], &["b"]));
+        assert_eq!(graph.node_order(), ["b", "a"]);
+
+        graph.add_node(node("a", &[], &["a"]));
+        graph.remove_node("b");
+        assert_eq!(graph.node_order(), ["a"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cyclic dependency")]
+    fn cycle_panics() {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node("a", &["x"], &["y"]));
+        graph.add_node(node("b", &["y"], &["x"]));
+        graph.node_order();
+    }
+}
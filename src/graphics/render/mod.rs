@@ -0,0 +1,473 @@
+use std::cell::Cell;
+
+use wgpu::BindGroup;
+use wgpu::Color;
+use wgpu::CommandEncoder;
+use wgpu::LoadOp;
+use wgpu::RenderPass;
+use wgpu::RenderPipeline;
+use wgpu::ShaderModuleDescriptor;
+use wgpu::StoreOp;
+use wgpu::TextureView;
+use winit::dpi::PhysicalSize;
+
+use super::Aabb;
+use super::AlphaMode;
+use super::BoundingSphere;
+use super::Camera;
+use super::ComponentInstances;
+use super::ComponentMesh;
+use super::ComponentModel;
+use super::EntityId;
+use super::MeshBatches;
+use super::Frustum;
+use super::ComponentRenderPipelineMesh;
+use super::ComponentRenderPipelineMeshInstanced;
+use super::ComponentRenderPipelineMeshUniform;
+use super::GameResource;
+use super::GameWorld;
+use super::GlobalTransform;
+use super::Id;
+use super::NoFrustumCulling;
+use super::RenderLayers;
+use super::WebGPUContext;
+use crate::{Mat4, Vec3};
+
+mod graph;
+pub use graph::*;
+
+mod sort;
+pub use sort::*;
+
+/// Name of the node [`GameWorld::new`] adds to the [`RenderGraph`], drawing
+/// every camera into the [`SURFACE`]
+pub const MAIN_NODE: &str = "main";
+
+/// Depth target of the [`MAIN_NODE`], cleared to the far plane every frame
+pub const MAIN_DEPTH: &str = "main_depth";
+
+/// How [`GameWorld::render`] starts and ends its pass, stored as a resource.
+///
+/// `load` applies where no camera clears: everywhere when there is no
+/// camera, outside the viewports otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    pub load:   LoadOp<Color>,
+    pub store:  StoreOp,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            load:   LoadOp::Clear(Color::BLACK),
+            store:  StoreOp::Store,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub fn with_clear_color(mut self, color: Color) -> Self {
+        self.load = LoadOp::Clear(color);
+        self
+    }
+}
+
+/// Counters of the last [`GameWorld::render`], summed over the cameras
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    drawn:  Cell<u32>,
+    culled: Cell<u32>,
+}
+
+impl RenderStats {
+
+    /// Draw items recorded, an instanced batch counts once
+    pub fn drawn(&self) -> u32 {
+        self.drawn.get()
+    }
+
+    /// Draw items skipped because they were outside the camera frustum
+    pub fn culled(&self) -> u32 {
+        self.culled.get()
+    }
+
+    fn reset(&self) {
+        self.drawn.set(0);
+        self.culled.set(0);
+    }
+
+    fn add(&self, drawn: u32, culled: u32) {
+        self.drawn.set(self.drawn.get() + drawn);
+        self.culled.set(self.culled.get() + culled);
+    }
+}
+
+/// One mesh to draw, or one batch of instances of it, with everything
+/// needed to bind it
+#[derive(Debug, Clone, Copy)]
+pub struct DrawItem {
+    pub pipeline:       Id,
+    /// Entity uniforms bound at group 2
+    pub bind_group:     Option<Id>,
+    pub model:          Option<Id>,
+    pub vertex_buffer:  Id,
+    pub vertex_count:   u32,
+    pub index_buffer:   Option<Id>,
+    pub index_count:    u32,
+    /// Instance vertex buffer bound at slot 1 instead of the model bind group
+    pub instances:      Option<Id>,
+    pub instance_count: u32,
+    pub layers:         RenderLayers,
+    /// World position the item is sorted by
+    pub position:       Vec3,
+    /// Drawn after opaque items, back to front
+    pub transparent:    bool,
+    /// World bounds tested against the camera frustum, `None` is never culled
+    pub bounds:         Option<(Aabb, BoundingSphere)>,
+}
+
+impl DrawItem {
+    fn new(mesh: &ComponentMesh, pipeline: Id, alpha_mode: AlphaMode, bind_group: Option<Id>, model: Option<&ComponentModel>, layers: Option<&RenderLayers>, global: Option<&GlobalTransform>) -> Self {
+        let index_count = mesh.indeces.as_ref().map_or(0, |i| i.len() as u32);
+        let matrix = global.map_or(Mat4::IDENTITY, |g| g.0);
+
+        Self {
+            pipeline,
+            bind_group,
+            model:          model.map(|m| m.bind_group),
+            vertex_buffer:  mesh.vertex_buffer,
+            vertex_count:   mesh.vertex.len() as u32,
+            index_buffer:   mesh.index_buffer,
+            index_count,
+            instances:      None,
+            instance_count: 1,
+            layers:         layers.copied().unwrap_or_default(),
+            position:       global.map_or(Vec3::ZERO, |g| g.translation()),
+            transparent:    alpha_mode.is_transparent(),
+            bounds:         Some((mesh.aabb.transformed(&matrix), mesh.sphere.transformed(&matrix))),
+        }
+    }
+
+    fn with_culling(mut self, culling: Option<&NoFrustumCulling>) -> Self {
+        if culling.is_some() {
+            self.bounds = None;
+        }
+        self
+    }
+
+    /// `false` when the item is entirely outside `frustum`
+    pub fn is_visible(&self, frustum: &Frustum) -> bool {
+        self.bounds.is_none_or(|(aabb, sphere)| frustum.intersects_sphere(&sphere) && frustum.intersects_aabb(&aabb))
+    }
+
+    /// The batch is culled as a whole by the bounds of all its instances,
+    /// and sorted by their center
+    fn with_instances(mut self, instances: &ComponentInstances) -> Self {
+        self.model = None;
+        self.bounds = instances.bounds;
+        if let Some((aabb, _)) = instances.bounds {
+            self.position = aabb.center();
+        }
+        self.instances = Some(instances.buffer);
+        self.instance_count = instances.count;
+        self
+    }
+
+    /// Records the draw, skipping the bindings `state` says are already set
+    fn record(&self, rpass: &mut RenderPass, res: &GameResource, state: &mut DrawState) {
+        if state.pipeline != Some(self.pipeline) {
+            rpass.set_pipeline(&res.render_pipeline[&self.pipeline]);
+            state.pipeline = Some(self.pipeline);
+        }
+        if state.vertex_buffer != Some(self.vertex_buffer) {
+            rpass.set_vertex_buffer(0, res.vertex_buffer[&self.vertex_buffer].slice(..));
+            state.vertex_buffer = Some(self.vertex_buffer);
+        }
+
+        match self.instances {
+            Some(instances) => if state.instances != Some(instances) {
+                rpass.set_vertex_buffer(1, res.vertex_buffer[&instances].slice(..));
+                state.instances = Some(instances);
+            },
+            None => if state.model != Some(self.model) {
+                rpass.set_bind_group(1, self.model.map_or(&res.identity_model, |m| &res.bind_group[&m]), &[]);
+                state.model = Some(self.model);
+            },
+        }
+        if let Some(bind_group) = self.bind_group.filter(|b| state.bind_group != Some(*b)) {
+            rpass.set_bind_group(2, &res.bind_group[&bind_group], &[]);
+            state.bind_group = Some(bind_group);
+        }
+
+        match self.index_buffer {
+            Some(index_buffer) => {
+                if state.index_buffer != Some(index_buffer) {
+                    rpass.set_index_buffer(res.index_buffer[&index_buffer].slice(..), wgpu::IndexFormat::Uint16);
+                    state.index_buffer = Some(index_buffer);
+                }
+                rpass.draw_indexed(0..self.index_count, 0, 0..self.instance_count);
+            }
+            None => rpass.draw(0..self.vertex_count, 0..self.instance_count),
+        }
+    }
+}
+
+/// Bindings currently set on a render pass, so consecutive draws sharing a
+/// pipeline, bind group or mesh don't set them again
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DrawState {
+    pub pipeline:       Option<Id>,
+    /// `Some(None)` is the identity model
+    pub model:          Option<Option<Id>>,
+    pub bind_group:     Option<Id>,
+    pub vertex_buffer:  Option<Id>,
+    pub instances:      Option<Id>,
+    pub index_buffer:   Option<Id>,
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Every drawable entity of any pipeline kind, unsorted, see
+    /// [`sort_draw_items`]. Instanced entities are batched into the item of
+    /// their source, and entities in the [`MeshBatches`] into the item of
+    /// their batch.
+    pub fn draw_list(&self) -> Vec<DrawItem> {
+        let mut items = vec![];
+        let batches = self.get_resource::<MeshBatches>();
+
+        for (entity, mesh, pipeline, model, layers, global, culling) in self.query::<(EntityId, &ComponentMesh, &ComponentRenderPipelineMesh, Option<&ComponentModel>, Option<&RenderLayers>, Option<&GlobalTransform>, Option<&NoFrustumCulling>)>() {
+            if batches.is_some_and(|b| b.batched.contains(&entity)) {
+                continue;
+            }
+            items.push(DrawItem::new(mesh, pipeline.id, pipeline.alpha_mode, None, model, layers, global).with_culling(culling));
+        }
+
+        for batch in batches.iter().flat_map(|b| &b.batches) {
+            if let Some(mesh) = self.get_component::<ComponentMesh>(batch.mesh) {
+                items.push(DrawItem::new(mesh, batch.pipeline, AlphaMode::Opaque, None, None, Some(&batch.layers), None).with_instances(&batch.instances));
+            }
+        }
+
+        for (mesh, pipeline, model, layers, global, culling) in self.query::<(&ComponentMesh, &ComponentRenderPipelineMeshUniform, Option<&ComponentModel>, Option<&RenderLayers>, Option<&GlobalTransform>, Option<&NoFrustumCulling>)>() {
+            items.push(DrawItem::new(mesh, pipeline.id, pipeline.alpha_mode, Some(pipeline.bind_group), model, layers, global).with_culling(culling));
+        }
+
+        // One instanced draw per source, its instances share its render layers
+        for (mesh, pipeline, instances, layers, global) in self.query::<(&ComponentMesh, &ComponentRenderPipelineMeshInstanced, &ComponentInstances, Option<&RenderLayers>, Option<&GlobalTransform>)>() {
+            items.push(DrawItem::new(mesh, pipeline.id, pipeline.alpha_mode, None, None, layers, global).with_instances(instances));
+        }
+
+        items
+    }
+
+    /// Runs the [`RenderGraph`] resource once per frame. Its default
+    /// [`MAIN_NODE`] draws every mesh for every active camera into the
+    /// surface in a single pass.
+    ///
+    /// ```ignore
+    /// App::new()
+    ///     .on_startup(|world| world.insert_resource(RenderSettings::default().with_clear_color(Color::WHITE)))
+    ///     .on_render(|world| world.render())
+    /// ```
+    pub fn render(&self) {
+        if let Some(stats) = self.get_resource::<RenderStats>() {
+            stats.reset();
+        }
+
+        match self.get_resource::<RenderGraph>() {
+            Some(graph) => graph.execute(self),
+            None => log::warn!("Error render: no RenderGraph resource"),
+        }
+    }
+
+    /// Adds the [`RenderGraph`] with its [`MAIN_NODE`] and the [`RenderStats`],
+    /// done once by [`GameWorld::new`]
+    pub(crate) fn add_render_graph(&mut self) {
+        let mut graph = RenderGraph::new();
+        graph.add_node(RenderNode::new(MAIN_NODE, |ctx| {
+            let items = ctx.world.draw_list();
+            let (view, size) = (ctx.view(SURFACE), ctx.size(SURFACE));
+            let depth = ctx.view(MAIN_DEPTH);
+
+            let stats = ctx.world.get_resource::<RenderStats>();
+
+            draw_cameras(ctx.world, ctx.res, ctx.encoder, view, depth, size, |rpass, res, camera_bind_group, layers, camera_view, view_proj| {
+                rpass.set_bind_group(0, camera_bind_group, &[]);
+
+                let frustum = Frustum::from_view_proj(&view_proj);
+                let seen = items.iter().filter(|item| item.layers.intersects(&layers));
+                let mut visible: Vec<DrawItem> = seen.clone().filter(|item| item.is_visible(&frustum)).copied().collect();
+
+                if let Some(stats) = stats {
+                    stats.add(visible.len() as u32, (seen.count() - visible.len()) as u32);
+                }
+                sort_draw_items(&mut visible, &camera_view);
+
+                let mut state = DrawState::default();
+                for item in &visible {
+                    item.record(rpass, res, &mut state);
+                }
+            });
+        }).writes(SURFACE).writes(MAIN_DEPTH));
+        graph.add_target(MAIN_DEPTH, TargetDesc::depth());
+
+        self.insert_resource(graph);
+        self.insert_resource(RenderStats::default());
+    }
+}
+
+pub trait SystemRenderMesh {
+    #[deprecated(note = "use GameWorld::render, which draws every pipeline kind in one pass")]
+    fn draw_mesh(&self);
+}
+
+impl SystemRenderMesh for GameWorld<'_> {
+    fn draw_mesh(&self) {
+        self.render();
+    }
+}
+
+
+pub trait SystemRenderMeshUniform {
+    #[deprecated(note = "use GameWorld::render, which draws every pipeline kind in one pass")]
+    fn draw_mesh_uniform(&self);
+}
+
+impl SystemRenderMeshUniform for GameWorld<'_> {
+    fn draw_mesh_uniform(&self) {
+        self.render();
+    }
+}
+
+/// One render pass into `view`, calling `draw` once per active camera in
+/// priority order with the camera bind group, the layers it sees, its view
+/// and its view-projection.
+/// Every camera is limited to its viewport and gets its own cleared depth in
+/// `depth`; without cameras everything is drawn once with an identity
+/// view-projection.
+fn draw_cameras(
+    world: &GameWorld,
+    res: &GameResource,
+    encoder: &mut CommandEncoder,
+    view: &TextureView,
+    depth: &TextureView,
+    size: PhysicalSize<u32>,
+    draw: impl Fn(&mut RenderPass, &GameResource, &BindGroup, RenderLayers, Mat4, Mat4),
+) {
+    let settings = world.get_resource::<RenderSettings>().copied().unwrap_or_default();
+    let cameras = world.active_cameras();
+
+    // The first camera clears with the pass when it covers the whole surface
+    let load = match cameras.first() {
+        Some((camera, _, _)) if camera.viewport.is_full() => camera.clear_color.map_or(settings.load, LoadOp::Clear),
+        _ => settings.load,
+    };
+
+    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Default Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load,
+                store: settings.store,
+            },
+        })],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth,
+            depth_ops: Some(wgpu::Operations {
+                load: LoadOp::Clear(1.0),
+                store: StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+
+    if cameras.is_empty() {
+        draw(&mut rpass, res, &res.identity_camera, RenderLayers::ALL, Mat4::IDENTITY, Mat4::IDENTITY);
+    }
+
+    for (i, (camera, uniform, global)) in cameras.iter().enumerate() {
+        let (Some((x, y, width, height)), Some((vx, vy, vw, vh))) = (camera.viewport.to_scissor(size), camera.viewport.to_viewport(size)) else {
+            continue;
+        };
+
+        rpass.set_viewport(vx, vy, vw, vh, 0.0, 1.0);
+        rpass.set_scissor_rect(x, y, width, height);
+
+        // The pass cleared the depth for the first camera, later cameras
+        // don't test against what the earlier ones drew
+        let cleared_by_pass = i == 0 && camera.viewport.is_full();
+        match (camera.clear_color, cleared_by_pass) {
+            (Some(color), false) => {
+                rpass.set_pipeline(&res.clear_pipeline);
+                rpass.set_blend_constant(color);
+                rpass.draw(0..3, 0..1);
+            }
+            (None, false) if i > 0 => {
+                rpass.set_pipeline(&res.clear_depth_pipeline);
+                rpass.draw(0..3, 0..1);
+            }
+            _ => (),
+        }
+
+        // Culled with the cropped projection the camera uniform holds
+        let global = global.copied().unwrap_or_default();
+        let camera_view = Camera::view_matrix(&global);
+        let view_proj = camera.viewport.crop(size) * camera.view_proj(&global);
+        draw(&mut rpass, res, &res.bind_group[&uniform.bind_group], camera.render_layers, camera_view, view_proj);
+    }
+}
+
+/// Pipeline filling the viewport with the blend constant and the far depth,
+/// used to clear the viewport of cameras that don't cover the whole surface.
+/// Without `color` only the depth is cleared.
+pub(crate) fn clear_pipeline(ctx: &WebGPUContext, color: bool) -> RenderPipeline {
+    let shader = ctx.device.create_shader_module(ShaderModuleDescriptor {
+        label:  Some("Clear Shader"),
+        source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("../../shaders/clear.wgsl"))),
+    });
+
+    let constant = wgpu::BlendComponent {
+        operation: wgpu::BlendOperation::Add,
+        src_factor: wgpu::BlendFactor::Constant,
+        dst_factor: wgpu::BlendFactor::Zero,
+    };
+
+    ctx.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Clear Pipeline"),
+        layout: None,
+
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: ctx.surface_format,
+                blend: Some(wgpu::BlendState { color: constant, alpha: constant }),
+                write_mask: if color { wgpu::ColorWrites::ALL } else { wgpu::ColorWrites::empty() },
+            })],
+        }),
+
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
@@ -0,0 +1,105 @@
+use super::DrawItem;
+use crate::Mat4;
+
+/// Distance of the item in front of the camera, along its view direction
+fn depth(item: &DrawItem, view: &Mat4) -> f32 {
+    -view.transform_point3(item.position).z
+}
+
+/// Orders what one camera sees: opaque items first, grouped by pipeline,
+/// bind group and mesh to save state changes and front to back inside a
+/// group; then transparent items back to front so they blend over what is
+/// behind them.
+pub fn sort_draw_items(items: &mut Vec<DrawItem>, view: &Mat4) {
+    let mut keyed: Vec<(f32, DrawItem)> = items.drain(..).map(|item| (depth(&item, view), item)).collect();
+
+    keyed.sort_by(|(a_depth, a), (b_depth, b)| {
+        a.transparent.cmp(&b.transparent).then_with(|| match a.transparent {
+            true => b_depth
+                .total_cmp(a_depth)
+                .then(a.pipeline.cmp(&b.pipeline)),
+            false => (a.pipeline, a.bind_group, a.vertex_buffer)
+                .cmp(&(b.pipeline, b.bind_group, b.vertex_buffer))
+                .then(a_depth.total_cmp(b_depth)),
+        })
+    });
+
+    items.extend(keyed.into_iter().map(|(_, item)| item));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::RenderLayers;
+    use crate::Vec3;
+
+    fn item(pipeline: i64, bind_group: i64, vertex_buffer: i64, z: f32, transparent: bool) -> DrawItem {
+        DrawItem {
+            pipeline,
+            bind_group:     Some(bind_group),
+            model:          None,
+            vertex_buffer,
+            vertex_count:   3,
+            index_buffer:   None,
+            index_count:    0,
+            instances:      None,
+            instance_count: 1,
+            layers:         RenderLayers::default(),
+            position:       Vec3::new(0.0, 0.0, z),
+            transparent,
+            bounds:         None,
+        }
+    }
+
+    fn keys(items: &[DrawItem]) -> Vec<(i64, Option<i64>, i64, f32)> {
+        items.iter().map(|i| (i.pipeline, i.bind_group, i.vertex_buffer, i.position.z)).collect()
+    }
+
+    /// Camera at `z = 10` looking down `-Z`, larger `z` is nearer
+    fn view() -> Mat4 {
+        Mat4::look_at_rh(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO, Vec3::Y)
+    }
+
+    #[test]
+    fn opaque_items_are_grouped_then_front_to_back() {
+        let mut items = vec![
+            item(2, 1, 1, 5.0, false),
+            item(1, 2, 1, 9.0, false),
+            item(1, 1, 2, 0.0, false),
+            item(1, 1, 1, -5.0, false),
+            item(1, 1, 1, 8.0, false),
+            item(2, 1, 1, 7.0, false),
+        ];
+        sort_draw_items(&mut items, &view());
+
+        assert_eq!(keys(&items), [
+            (1, Some(1), 1, 8.0),
+            (1, Some(1), 1, -5.0),
+            (1, Some(1), 2, 0.0),
+            (1, Some(2), 1, 9.0),
+            (2, Some(1), 1, 7.0),
+            (2, Some(1), 1, 5.0),
+        ]);
+    }
+
+    #[test]
+    fn transparent_items_follow_back_to_front() {
+        let mut items = vec![
+            item(1, 1, 1, 5.0, true),
+            item(3, 1, 1, 0.0, false),
+            item(2, 2, 2, -5.0, true),
+            item(1, 1, 1, 9.0, true),
+        ];
+        sort_draw_items(&mut items, &view());
+
+        assert_eq!(keys(&items), [
+            (3, Some(1), 1, 0.0),
+            (2, Some(2), 2, -5.0),
+            (1, Some(1), 1, 5.0),
+            (1, Some(1), 1, 9.0),
+        ]);
+        assert!(!items[0].transparent);
+        assert!(items[1..].iter().all(|i| i.transparent));
+    }
+}
@@ -0,0 +1,112 @@
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+
+use super::GameWorld;
+
+/// Singletons of arbitrary types, at most one value per type.
+#[derive(Default)]
+pub struct Resources {
+    values: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Resources {
+
+    /// Inserts or replaces the value of type `T`, returning the old value
+    pub fn insert<T: 'static>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|old| *old.downcast::<T>().unwrap())
+    }
+
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .map(|old| *old.downcast::<T>().unwrap())
+    }
+
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>()).map(|v| v.downcast_ref::<T>().unwrap())
+    }
+
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.values.get_mut(&TypeId::of::<T>()).map(|v| v.downcast_mut::<T>().unwrap())
+    }
+
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Inserts a global resource, replacing the previous value of the same type.
+    ///
+    /// ```ignore
+    /// world.insert_resource(Settings { volume: 0.5 });
+    /// let volume = world.resource::<Settings>().volume;
+    /// ```
+    pub fn insert_resource<T: 'static>(&mut self, value: T) -> Option<T> {
+        self.resources.insert(value)
+    }
+
+    /// Inserts `T::default()` unless a `T` already exists
+    pub fn init_resource<T: Default + 'static>(&mut self) {
+        if !self.resources.contains::<T>() {
+            self.resources.insert(T::default());
+        }
+    }
+
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        self.resources.remove::<T>()
+    }
+
+    pub fn contains_resource<T: 'static>(&self) -> bool {
+        self.resources.contains::<T>()
+    }
+
+    /// Panics if there is no `T`, use [`get_resource`](Self::get_resource) otherwise
+    pub fn resource<T: 'static>(&self) -> &T {
+        self.resources
+            .get::<T>()
+            .unwrap_or_else(|| panic!("Error resource {} does not exist", type_name::<T>()))
+    }
+
+    /// Panics if there is no `T`, use [`get_resource_mut`](Self::get_resource_mut) otherwise
+    pub fn resource_mut<T: 'static>(&mut self) -> &mut T {
+        self.resources
+            .get_mut::<T>()
+            .unwrap_or_else(|| panic!("Error resource {} does not exist", type_name::<T>()))
+    }
+
+    pub fn get_resource<T: 'static>(&self) -> Option<&T> {
+        self.resources.get::<T>()
+    }
+
+    pub fn get_resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut::<T>()
+    }
+
+    /// Takes the `T` out of the world while `f` runs, so it can be used
+    /// together with the rest of the world.
+    ///
+    /// ```ignore
+    /// world.resource_scope(|world, actions: &mut ActionMap| actions.update(world.resource::<Input>()));
+    /// ```
+    pub fn resource_scope<T: 'static, R>(&mut self, f: impl FnOnce(&mut Self, &mut T) -> R) -> R {
+        let mut value = self.resources
+            .remove::<T>()
+            .unwrap_or_else(|| panic!("Error resource {} does not exist", type_name::<T>()));
+
+        let result = f(self, &mut value);
+        self.resources.insert(value);
+        result
+    }
+}
@@ -0,0 +1,140 @@
+use std::ops::Range;
+
+use bytemuck::Pod;
+use wgpu::{Buffer, BufferDescriptor, BufferSlice, BufferUsages, Device, Queue};
+
+use super::GameWorld;
+
+/// Part of the [`GeometryRing`] buffer written this frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingSlice {
+    pub offset: u64,
+    pub size:   u64,
+}
+
+impl RingSlice {
+
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.offset + self.size
+    }
+
+    pub fn slice<'b>(&self, buffer: &'b Buffer) -> BufferSlice<'b> {
+        buffer.slice(self.range())
+    }
+}
+
+/// Vertex and index buffer for geometry rebuilt every frame, such as trails
+/// and debug lines. Pushes go one after the other around the buffer, and a
+/// frame may use the whole buffer since earlier frames were already drawn
+/// when their writes get overwritten.
+///
+/// ```ignore
+/// world.insert_geometry_ring(1 << 20);
+///
+/// // In a system
+/// let lines = world.push_geometry(&trail_vertices);
+///
+/// // In a render node
+/// let ring = ctx.world.resource::<GeometryRing>();
+/// rpass.set_vertex_buffer(0, lines.unwrap().slice(ring.buffer()));
+/// ```
+#[derive(Debug)]
+pub struct GeometryRing {
+    buffer:         Buffer,
+    head:           u64,
+    /// Bytes pushed since [`GeometryRing::begin_frame`]
+    used:           u64,
+}
+
+impl GeometryRing {
+
+    pub fn new(device: &Device, capacity: u64) -> Self {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label:              Some("Geometry Ring"),
+            size:               capacity.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            usage:              BufferUsages::VERTEX | BufferUsages::INDEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self { buffer, head: 0, used: 0 }
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.buffer.size()
+    }
+
+    /// Bytes still free this frame
+    pub fn remaining(&self) -> u64 {
+        self.capacity() - self.used
+    }
+
+    /// Writes `data` after the previous push, wrapping to the start of the
+    /// buffer when it doesn't fit before the end. `None` when `data` is empty,
+    /// since wgpu can't bind an empty slice, or when this frame already
+    /// filled the buffer.
+    pub fn push<T: Pod>(&mut self, queue: &Queue, data: &[T]) -> Option<RingSlice> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        let len = bytes.len() as u64;
+        if len == 0 {
+            return None;
+        }
+        let size = len.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+
+        let mut offset = self.head;
+        // Skipped bytes at the end of the buffer count as used
+        let skipped = if offset + size > self.capacity() { self.capacity() - offset } else { 0 };
+        if skipped > 0 {
+            offset = 0;
+        }
+
+        if self.used + skipped + size > self.capacity() {
+            log::warn!("Error geometry ring is full: {} bytes pushed this frame, {} more asked", self.used, size);
+            return None;
+        }
+
+        let mut padded;
+        let bytes = if size == len {
+            bytes
+        } else {
+            padded = bytes.to_vec();
+            padded.resize(size as usize, 0);
+            &padded
+        };
+
+        queue.write_buffer(&self.buffer, offset, bytes);
+        self.used += skipped + size;
+        self.head = (offset + size) % self.capacity();
+
+        Some(RingSlice { offset, size: len })
+    }
+
+    /// Frees the space of the previous frame, called by the [`App`](crate::App)
+    /// runner after rendering
+    pub fn begin_frame(&mut self) {
+        self.used = 0;
+    }
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Inserts a [`GeometryRing`] resource of `capacity` bytes
+    pub fn insert_geometry_ring(&mut self, capacity: u64) {
+        let ring = GeometryRing::new(&self.resource.borrow().ctx.device, capacity);
+        self.insert_resource(ring);
+    }
+
+    /// Pushes `data` into the [`GeometryRing`] resource for this frame, see [`GeometryRing::push`]
+    pub fn push_geometry<T: Pod>(&mut self, data: &[T]) -> Option<RingSlice> {
+        let ctx = self.resource.borrow().ctx.clone();
+        let Some(ring) = self.get_resource_mut::<GeometryRing>() else {
+            log::warn!("Error push geometry: no GeometryRing resource, see GameWorld::insert_geometry_ring");
+            return None;
+        };
+
+        ring.push(&ctx.queue, data)
+    }
+}
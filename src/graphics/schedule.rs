@@ -0,0 +1,421 @@
+use std::collections::BTreeMap;
+
+use log::warn;
+
+use super::{clamp_tick, Access, GameWorld, WorldQuery};
+
+/// Stages run in this order, `Startup` only once before the first frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    Startup,
+    PreUpdate,
+    Update,
+    PostUpdate,
+    Render,
+}
+
+impl Stage {
+    /// Stages run every frame, in order
+    pub const FRAME: [Stage; 4] = [Stage::PreUpdate, Stage::Update, Stage::PostUpdate, Stage::Render];
+}
+
+type SystemFn = Box<dyn FnMut(&mut GameWorld)>;
+
+/// A named system with its declared data access and ordering constraints.
+///
+/// ```ignore
+/// world.add_system(Stage::Update, System::new("movement", movement)
+///     .query::<(&mut Transform, &Velocity)>()
+///     .after("input"));
+/// ```
+pub struct System {
+    name:   String,
+    run:    SystemFn,
+    access: Access,
+    before: Vec<String>,
+    after:  Vec<String>,
+    /// Change tick of the end of the previous run, `Added`/`Changed` see
+    /// everything newer
+    last_run: Option<u32>,
+}
+
+impl System {
+
+    pub fn new(name: &str, run: impl FnMut(&mut GameWorld) + 'static) -> Self {
+        Self {
+            name:   name.to_string(),
+            run:    Box::new(run),
+            access: Access::default(),
+            before: vec![],
+            after:  vec![],
+            last_run: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn access(&self) -> &Access {
+        &self.access
+    }
+
+    pub fn reads<T: 'static>(mut self) -> Self {
+        self.access.add_read::<T>();
+        self
+    }
+
+    pub fn writes<T: 'static>(mut self) -> Self {
+        self.access.add_write::<T>();
+        self
+    }
+
+    pub fn reads_resource<T: 'static>(mut self) -> Self {
+        self.access.add_resource_read::<T>();
+        self
+    }
+
+    pub fn writes_resource<T: 'static>(mut self) -> Self {
+        self.access.add_resource_write::<T>();
+        self
+    }
+
+    /// Declares the access of a query the system runs, e.g. `(&mut A, &B)`
+    pub fn query<Q: WorldQuery>(mut self) -> Self {
+        Q::update_access(&mut self.access);
+        self
+    }
+
+    /// Runs this system before the named system of the same stage
+    pub fn before(mut self, name: &str) -> Self {
+        self.before.push(name.to_string());
+        self
+    }
+
+    /// Runs this system after the named system of the same stage
+    pub fn after(mut self, name: &str) -> Self {
+        self.after.push(name.to_string());
+        self
+    }
+}
+
+/// Two systems of a stage that touch the same data, at least one of them
+/// writing, with no ordering between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemConflict {
+    pub stage:  Stage,
+    pub first:  String,
+    pub second: String,
+    pub types:  Vec<&'static str>,
+}
+
+#[derive(Default)]
+struct StageSystems {
+    systems:    Vec<System>,
+    order:      Option<Vec<usize>>,
+}
+
+impl StageSystems {
+
+    /// `edges[a]` holds the systems that must run after `a`
+    fn edges(&self) -> Vec<Vec<usize>> {
+        let index = |name: &str| self.systems.iter().position(|s| s.name == name);
+        let mut edges = vec![vec![]; self.systems.len()];
+
+        for (i, system) in self.systems.iter().enumerate() {
+            for name in &system.before {
+                match index(name) {
+                    Some(j) => edges[i].push(j),
+                    None => warn!("System `{}` runs before unknown system `{}`", system.name, name),
+                }
+            }
+            for name in &system.after {
+                match index(name) {
+                    Some(j) => edges[j].push(i),
+                    None => warn!("System `{}` runs after unknown system `{}`", system.name, name),
+                }
+            }
+        }
+
+        edges
+    }
+
+    /// Topological order that keeps insertion order between unrelated systems
+    fn order(&mut self, stage: Stage) -> &[usize] {
+        if self.order.is_none() {
+            let edges = self.edges();
+            let mut incoming = vec![0; self.systems.len()];
+            for targets in &edges {
+                for &t in targets {
+                    incoming[t] += 1;
+                }
+            }
+
+            let mut order = Vec::with_capacity(self.systems.len());
+            let mut done = vec![false; self.systems.len()];
+
+            while order.len() < self.systems.len() {
+                let next = (0..self.systems.len())
+                    .find(|&i| !done[i] && incoming[i] == 0)
+                    .unwrap_or_else(|| {
+                        let cycle: Vec<&str> = (0..self.systems.len())
+                            .filter(|&i| !done[i])
+                            .map(|i| self.systems[i].name.as_str())
+                            .collect();
+                        panic!("Systems of stage {:?} have a cyclic ordering: {:?}", stage, cycle)
+                    });
+
+                done[next] = true;
+                order.push(next);
+                for &t in &edges[next] {
+                    incoming[t] -= 1;
+                }
+            }
+
+            self.order = Some(order);
+
+            for conflict in self.conflicts(stage) {
+                warn!(
+                    "Systems `{}` and `{}` of stage {:?} both access {:?} without an ordering",
+                    conflict.first, conflict.second, stage, conflict.types
+                );
+            }
+        }
+
+        self.order.as_ref().unwrap()
+    }
+
+    fn conflicts(&self, stage: Stage) -> Vec<SystemConflict> {
+        let edges = self.edges();
+        let n = self.systems.len();
+
+        // reachable[a][b]: a is ordered before b, directly or transitively
+        let mut reachable = vec![vec![false; n]; n];
+        for (targets, reach) in edges.iter().zip(&mut reachable) {
+            let mut stack = targets.clone();
+            while let Some(i) = stack.pop() {
+                if !reach[i] {
+                    reach[i] = true;
+                    stack.extend_from_slice(&edges[i]);
+                }
+            }
+        }
+
+        let mut conflicts = vec![];
+        for (a, first) in self.systems.iter().enumerate() {
+            for (b, second) in self.systems.iter().enumerate().skip(a + 1) {
+                if reachable[a][b] || reachable[b][a] {
+                    continue;
+                }
+
+                let types = first.access.conflicts(&second.access);
+                if !types.is_empty() {
+                    conflicts.push(SystemConflict {
+                        stage,
+                        first:  first.name.clone(),
+                        second: second.name.clone(),
+                        types,
+                    });
+                }
+            }
+        }
+
+        conflicts
+    }
+}
+
+/// Systems grouped into ordered [`Stage`]s.
+#[derive(Default)]
+pub struct Schedule {
+    stages:         BTreeMap<Stage, StageSystems>,
+    startup_done:   bool,
+}
+
+impl Schedule {
+
+    pub fn add_system(&mut self, stage: Stage, system: System) {
+        let stage = self.stages.entry(stage).or_default();
+        stage.systems.push(system);
+        stage.order = None;
+    }
+
+    /// Pairs of systems whose read/write access overlaps without a
+    /// `before`/`after` constraint between them, in every stage.
+    pub fn conflicts(&self) -> Vec<SystemConflict> {
+        self.stages
+            .iter()
+            .flat_map(|(stage, systems)| systems.conflicts(*stage))
+            .collect()
+    }
+
+    /// System names of a stage in the order they run.
+    /// Panics if the ordering constraints have a cycle.
+    pub fn system_order(&mut self, stage: Stage) -> Vec<String> {
+        let Some(systems) = self.stages.get_mut(&stage) else {
+            return vec![];
+        };
+        let order = systems.order(stage).to_vec();
+        order.iter().map(|&i| systems.systems[i].name.clone()).collect()
+    }
+
+    /// Runs the systems of a stage, then applies the commands they queued
+    pub fn run_stage(&mut self, stage: Stage, world: &mut GameWorld) {
+        let Some(systems) = self.stages.get_mut(&stage) else {
+            world.apply_commands();
+            return;
+        };
+
+        let order = systems.order(stage).to_vec();
+        for i in order {
+            let system = &mut systems.systems[i];
+
+            // Each system sees the changes made since it last ran, including
+            // those made later in the previous frame
+            let frame_start = world.components.last_change_tick();
+            let last_run = system.last_run.unwrap_or(frame_start);
+            world.components.set_last_change_tick(last_run);
+            world.components.increment_change_tick();
+
+            (system.run)(world);
+
+            // Later changes, also from outside systems, get a newer tick
+            system.last_run = Some(world.components.change_tick());
+            world.components.increment_change_tick();
+            world.components.set_last_change_tick(frame_start);
+        }
+
+        world.apply_commands();
+    }
+
+    /// Runs the startup stage the first time it is called, later calls do nothing
+    pub fn run_startup(&mut self, world: &mut GameWorld) {
+        if !self.startup_done {
+            self.startup_done = true;
+            self.run_stage(Stage::Startup, world);
+        }
+    }
+
+    /// Runs every per-frame stage once
+    pub fn run_frame(&mut self, world: &mut GameWorld) {
+        for stage in Stage::FRAME {
+            self.run_stage(stage, world);
+        }
+
+        if world.components.check_change_ticks() {
+            let current = world.components.change_tick();
+            for systems in self.stages.values_mut() {
+                for system in systems.systems.iter_mut() {
+                    if let Some(last_run) = &mut system.last_run {
+                        clamp_tick(last_run, current);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Moves systems added to `other` (e.g. while this schedule was running)
+    /// into this schedule
+    fn merge(&mut self, other: Schedule) {
+        for (stage, systems) in other.stages {
+            for system in systems.systems {
+                self.add_system(stage, system);
+            }
+        }
+    }
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    pub fn add_system(&mut self, stage: Stage, system: System) {
+        self.schedule.add_system(stage, system);
+    }
+
+    /// Runs one stage of the world's schedule. Systems may add more systems
+    /// while running, they take effect from the next run.
+    pub fn run_stage(&mut self, stage: Stage) {
+        self.with_schedule(|schedule, world| schedule.run_stage(stage, world));
+    }
+
+    pub fn run_startup(&mut self) {
+        self.with_schedule(|schedule, world| schedule.run_startup(world));
+    }
+
+    /// Runs the per-frame stages once, called by the [`App`](crate::App) runner every frame
+    pub fn run_schedule(&mut self) {
+        self.with_schedule(|schedule, world| schedule.run_frame(world));
+    }
+
+    fn with_schedule(&mut self, f: impl FnOnce(&mut Schedule, &mut Self)) {
+        let mut schedule = std::mem::take(&mut self.schedule);
+        f(&mut schedule, self);
+
+        let added = std::mem::replace(&mut self.schedule, schedule);
+        self.schedule.startup_done |= added.startup_done;
+        self.schedule.merge(added);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct A;
+    struct B;
+
+    fn system(name: &str) -> System {
+        System::new(name, |_| {})
+    }
+
+    #[test]
+    fn stages_run_in_declared_order() {
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Render, system("render"));
+        schedule.add_system(Stage::PreUpdate, system("input"));
+        schedule.add_system(Stage::Startup, system("setup"));
+        schedule.add_system(Stage::PostUpdate, system("propagate"));
+        schedule.add_system(Stage::Update, system("movement"));
+
+        let stages: Vec<Stage> = schedule.stages.keys().copied().collect();
+        assert_eq!(stages, [&[Stage::Startup][..], &Stage::FRAME].concat());
+    }
+
+    #[test]
+    fn before_and_after_order_systems() {
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, system("draw").after("move"));
+        schedule.add_system(Stage::Update, system("move").after("input"));
+        schedule.add_system(Stage::Update, system("log"));
+        schedule.add_system(Stage::Update, system("input").before("draw"));
+
+        assert_eq!(schedule.system_order(Stage::Update), ["log", "input", "move", "draw"]);
+        assert!(schedule.system_order(Stage::Render).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "cyclic ordering")]
+    fn cyclic_ordering_panics() {
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, system("a").before("b"));
+        schedule.add_system(Stage::Update, system("b").before("c"));
+        schedule.add_system(Stage::Update, system("c").before("a"));
+        schedule.system_order(Stage::Update);
+    }
+
+    #[test]
+    fn conflicts_need_a_write_and_no_ordering() {
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::Update, system("write_a").writes::<A>());
+        schedule.add_system(Stage::Update, system("read_a").reads::<A>().reads::<B>());
+        schedule.add_system(Stage::Update, system("read_b").reads::<B>());
+        schedule.add_system(Stage::Update, system("write_b").query::<&mut B>().after("read_a").after("read_b"));
+        schedule.add_system(Stage::PostUpdate, system("write_a_later").writes::<A>());
+
+        let conflicts = schedule.conflicts();
+        assert_eq!(conflicts, [SystemConflict {
+            stage:  Stage::Update,
+            first:  "write_a".to_string(),
+            second: "read_a".to_string(),
+            types:  vec![std::any::type_name::<A>()],
+        }]);
+    }
+}
@@ -1,78 +1,117 @@
-use wgpu::ShaderModuleDescriptor;
-
-use crate::id;
-
-use super::{Entity, Id};
-
-
-pub trait SystemShaderMesh {
-    fn add_shader_mesh(&mut self);
-}
-
-impl SystemShaderMesh for Entity<'_> {
-    fn add_shader_mesh(&mut self) {
-        self.add_component(ComponentShaderMesh::new(self));
-    }
-}
-
-pub struct ComponentShaderMesh {
-    pub id: Id
-}
-
-impl ComponentShaderMesh {
-    fn new(entity: &Entity) -> Self {
-        let mut res = entity.game_resource.borrow_mut();
-        const mesh: &str = include_str!("../shaders/mesh.wgsl");
-
-        let shader = res.ctx.device.create_shader_module(ShaderModuleDescriptor {
-            label:  None,
-            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(mesh)),
-        });
-
-        let id = id();
-        res.shader.insert(id, shader);
-
-        Self {
-            id
-        }
-
-    }
-}
-
-pub trait SystemShaderMeshUniform {
-    fn add_shader_mesh_uniform(&mut self);
-}
-
-impl SystemShaderMeshUniform for Entity<'_> {
-    fn add_shader_mesh_uniform(&mut self) {
-        self.add_component(ComponentShaderMeshUniform::new(self));
-    }
-}
-
-pub struct ComponentShaderMeshUniform {
-    pub id: Id
-}
-
-impl ComponentShaderMeshUniform {
-    fn new(entity: &Entity) -> Self {
-        let mut res = entity.game_resource.borrow_mut();
-        const mesh: &str = include_str!("../shaders/mesh_uniform.wgsl");
-
-        let shader = res.ctx.device.create_shader_module(ShaderModuleDescriptor {
-            label:  None,
-            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(mesh)),
-        });
-
-        let id = id();
-        res.shader.insert(id, shader);
-
-        Self {
-            id
-        }
-
-    }
-}
-
-struct ComponentCustomShader {
-    id: Id
+use wgpu::{Device, ShaderModule, ShaderModuleDescriptor};
+
+use crate::id;
+
+use super::{Entity, Id};
+
+
+pub trait SystemShaderMesh {
+    fn add_shader_mesh(&mut self);
+}
+
+impl SystemShaderMesh for Entity<'_, '_> {
+    fn add_shader_mesh(&mut self) {
+        self.add_component(ComponentShaderMesh::new(self));
+    }
+}
+
+pub struct ComponentShaderMesh {
+    pub id: Id
+}
+
+impl ComponentShaderMesh {
+    fn new(entity: &Entity) -> Self {
+        let mut res = entity.game_resource().borrow_mut();
+        const mesh: &str = include_str!("../shaders/mesh.wgsl");
+
+        let shader = res.ctx.device.create_shader_module(ShaderModuleDescriptor {
+            label:  None,
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(mesh)),
+        });
+
+        let id = id();
+        res.shader.insert(id, shader);
+
+        Self {
+            id
+        }
+
+    }
+}
+
+pub trait SystemShaderMeshUniform {
+    fn add_shader_mesh_uniform(&mut self);
+}
+
+impl SystemShaderMeshUniform for Entity<'_, '_> {
+    fn add_shader_mesh_uniform(&mut self) {
+        self.add_component(ComponentShaderMeshUniform::new(self));
+    }
+}
+
+pub struct ComponentShaderMeshUniform {
+    pub id: Id
+}
+
+impl ComponentShaderMeshUniform {
+    fn new(entity: &Entity) -> Self {
+        let mut res = entity.game_resource().borrow_mut();
+        const mesh: &str = include_str!("../shaders/mesh_uniform.wgsl");
+
+        let shader = res.ctx.device.create_shader_module(ShaderModuleDescriptor {
+            label:  None,
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(mesh)),
+        });
+
+        let id = id();
+        res.shader.insert(id, shader);
+
+        Self {
+            id
+        }
+
+    }
+}
+
+pub trait SystemShaderMeshInstanced {
+    fn add_shader_mesh_instanced(&mut self);
+}
+
+impl SystemShaderMeshInstanced for Entity<'_, '_> {
+    fn add_shader_mesh_instanced(&mut self) {
+        self.add_component(ComponentShaderMeshInstanced::new(self));
+    }
+}
+
+pub struct ComponentShaderMeshInstanced {
+    pub id: Id
+}
+
+impl ComponentShaderMeshInstanced {
+    fn new(entity: &Entity) -> Self {
+        let mut res = entity.game_resource().borrow_mut();
+        let shader = mesh_instanced_shader(&res.ctx.device);
+
+        let id = id();
+        res.shader.insert(id, shader);
+
+        Self {
+            id
+        }
+
+    }
+}
+
+/// Module of `mesh_instanced.wgsl`, also used by the [`MeshBatches`](super::MeshBatches) pipeline
+pub(crate) fn mesh_instanced_shader(device: &Device) -> ShaderModule {
+    const mesh: &str = include_str!("../shaders/mesh_instanced.wgsl");
+
+    device.create_shader_module(ShaderModuleDescriptor {
+        label:  Some("Mesh Instanced Shader"),
+        source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(mesh)),
+    })
+}
+
+struct ComponentCustomShader {
+    id: Id
 }
\ No newline at end of file
@@ -0,0 +1,383 @@
+//! Indexed meshes of common primitives, centered on the origin with `Y` up
+//! and counter-clockwise front faces.
+//!
+//! Indices are `u16`, so a shape has at most [`MAX_VERTICES`] vertices.
+//! Subdivisions asking for more are lowered until the shape fits, with a warning.
+//!
+//! ```ignore
+//! let (vertex, index) = shapes::sphere(0.5, 32, 16).into_mesh([1.0, 0.5, 0.2]);
+//! entity.add_mesh(vertex, index);
+//! ```
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use super::Vertex3D;
+use crate::{Vec2, Vec3};
+
+/// Most vertices of a shape, the count `u16` indices can address
+pub const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+/// Vertex attributes and triangle list indices of a generated shape
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub positions:  Vec<[f32; 3]>,
+    pub normals:    Vec<[f32; 3]>,
+    pub uvs:        Vec<[f32; 2]>,
+    /// Tangent and handedness in `w`, filled by [`MeshData::with_tangents`]
+    pub tangents:   Option<Vec<[f32; 4]>>,
+    pub indices:    Vec<u16>,
+}
+
+impl MeshData {
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Vertices of one color and the indices, as taken by [`SystemMesh::add_mesh`](super::SystemMesh::add_mesh)
+    pub fn into_mesh(self, color: [f32; 3]) -> (Vec<Vertex3D>, Option<Vec<u16>>) {
+        let vertex = self.positions.iter().map(|&pos| Vertex3D { pos, color }).collect();
+        (vertex, Some(self.indices))
+    }
+
+    /// Like [`MeshData::into_mesh`] with the normals mapped to colors, to see
+    /// the shape without lighting
+    pub fn into_mesh_normal_colors(self) -> (Vec<Vertex3D>, Option<Vec<u16>>) {
+        let vertex = self.positions
+            .iter()
+            .zip(&self.normals)
+            .map(|(&pos, n)| Vertex3D { pos, color: (Vec3::from_array(*n) * 0.5 + 0.5).to_array() })
+            .collect();
+        (vertex, Some(self.indices))
+    }
+
+    /// Computes per-vertex tangents along the `u` texture direction
+    pub fn with_tangents(mut self) -> Self {
+        let count = self.vertex_count();
+        let mut tangents = vec![Vec3::ZERO; count];
+        let mut bitangents = vec![Vec3::ZERO; count];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            let (pa, pb, pc) = (Vec3::from(self.positions[a]), Vec3::from(self.positions[b]), Vec3::from(self.positions[c]));
+            let (ua, ub, uc) = (Vec2::from(self.uvs[a]), Vec2::from(self.uvs[b]), Vec2::from(self.uvs[c]));
+
+            let (e1, e2) = (pb - pa, pc - pa);
+            let (d1, d2) = (ub - ua, uc - ua);
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let tangent = (e1 * d2.y - e2 * d1.y) / det;
+            let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+            for i in [a, b, c] {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        let tangents = (0..count)
+            .map(|i| {
+                let normal = Vec3::from(self.normals[i]);
+                // Gram-Schmidt against the normal, any perpendicular axis when the uvs are degenerate
+                let tangent = (tangents[i] - normal * normal.dot(tangents[i])).try_normalize().unwrap_or_else(|| normal.any_orthonormal_vector());
+                let handedness = if normal.cross(tangent).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+                tangent.extend(handedness).to_array()
+            })
+            .collect();
+
+        self.tangents = Some(tangents);
+        self
+    }
+
+    fn push(&mut self, position: Vec3, normal: Vec3, uv: Vec2) -> u16 {
+        let index = self.positions.len();
+        if index >= MAX_VERTICES {
+            panic!("Error shape has more than {} vertices", MAX_VERTICES);
+        }
+
+        self.positions.push(position.to_array());
+        self.normals.push(normal.to_array());
+        self.uvs.push(uv.to_array());
+        index as u16
+    }
+
+    /// `(columns + 1) * (rows + 1)` vertices from `vertex(column, row)`, two
+    /// triangles per cell facing along `d/dcolumn × d/drow`
+    fn grid(&mut self, columns: u32, rows: u32, vertex: impl Fn(u32, u32) -> (Vec3, Vec3, Vec2)) {
+        let first = self.positions.len() as u32;
+
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (position, normal, uv) = vertex(column, row);
+                self.push(position, normal, uv);
+            }
+        }
+
+        let at = |column: u32, row: u32| (first + row * (columns + 1) + column) as u16;
+        for row in 0..rows {
+            for column in 0..columns {
+                let (a, b) = (at(column, row), at(column + 1, row));
+                let (c, d) = (at(column, row + 1), at(column + 1, row + 1));
+                self.indices.extend_from_slice(&[a, b, c, b, d, c]);
+            }
+        }
+    }
+
+    /// Triangle fan around `center`, facing `normal`, over `ring` positions
+    /// given counter-clockwise when seen from the front
+    fn fan(&mut self, center: Vec3, normal: Vec3, ring: impl Iterator<Item = (Vec3, Vec2)>) {
+        let center = self.push(center, normal, Vec2::splat(0.5));
+        let ring: Vec<u16> = ring.map(|(position, uv)| self.push(position, normal, uv)).collect();
+
+        for pair in ring.windows(2) {
+            self.indices.extend_from_slice(&[center, pair[0], pair[1]]);
+        }
+    }
+}
+
+/// Lowers the larger of the two segment counts until `vertices` of them fit
+/// in [`MAX_VERTICES`]
+fn fit(shape: &str, (mut a, mut b): (u32, u32), vertices: impl Fn(usize, usize) -> usize) -> (u32, u32) {
+    let asked = (a, b);
+    a = a.min(MAX_VERTICES as u32);
+    b = b.min(MAX_VERTICES as u32);
+
+    while vertices(a as usize, b as usize) > MAX_VERTICES {
+        if a >= b { a -= 1 } else { b -= 1 }
+    }
+
+    if (a, b) != asked {
+        log::warn!("Error {} of {:?} segments has more than {} vertices, using {:?}", shape, asked, MAX_VERTICES, (a, b));
+    }
+    (a, b)
+}
+
+/// Box of `size` with every face split into `subdivisions` × `subdivisions`
+/// cells, `6 * (subdivisions + 1)²` vertices so at most 103 subdivisions
+pub fn cuboid(size: Vec3, subdivisions: u32) -> MeshData {
+    let (subdivisions, _) = fit("cuboid", (subdivisions.max(1), 0), |n, _| 6 * (n + 1) * (n + 1));
+    let half = size * 0.5;
+    let mut mesh = MeshData::default();
+
+    // Normal, then the face's u and v axes with u × v = normal
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+        (Vec3::Z, Vec3::X, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+    ];
+
+    for (normal, u, v) in faces {
+        mesh.grid(subdivisions, subdivisions, |column, row| {
+            let (s, t) = (column as f32 / subdivisions as f32, row as f32 / subdivisions as f32);
+            let position = (normal + u * (s * 2.0 - 1.0) + v * (t * 2.0 - 1.0)) * half;
+            (position, normal, Vec2::new(s, 1.0 - t))
+        });
+    }
+
+    mesh
+}
+
+/// Cube with edges of `size`, see [`cuboid`]
+pub fn cube(size: f32, subdivisions: u32) -> MeshData {
+    cuboid(Vec3::splat(size), subdivisions)
+}
+
+/// UV sphere of `sectors` around `Y` and `stacks` from pole to pole,
+/// `(sectors + 1) * (stacks + 1)` vertices
+pub fn sphere(radius: f32, sectors: u32, stacks: u32) -> MeshData {
+    let (sectors, stacks) = fit("sphere", (sectors.max(3), stacks.max(2)), |s, t| (s + 1) * (t + 1));
+    let mut mesh = MeshData::default();
+
+    mesh.grid(sectors, stacks, |column, row| {
+        let (s, t) = (column as f32 / sectors as f32, row as f32 / stacks as f32);
+        let (phi, theta) = (s * TAU, t * PI);
+        let normal = Vec3::new(theta.sin() * phi.cos(), -theta.cos(), -theta.sin() * phi.sin());
+        (normal * radius, normal, Vec2::new(s, 1.0 - t))
+    });
+
+    mesh
+}
+
+/// Square of `size` in the `XZ` plane facing `Y`, `(subdivisions + 1)²`
+/// vertices so at most 255 subdivisions
+pub fn plane(size: Vec2, subdivisions: u32) -> MeshData {
+    let (subdivisions, _) = fit("plane", (subdivisions.max(1), 0), |n, _| (n + 1) * (n + 1));
+    let mut mesh = MeshData::default();
+
+    mesh.grid(subdivisions, subdivisions, |column, row| {
+        let (s, t) = (column as f32 / subdivisions as f32, row as f32 / subdivisions as f32);
+        let position = Vec3::new((s - 0.5) * size.x, 0.0, (0.5 - t) * size.y);
+        (position, Vec3::Y, Vec2::new(s, 1.0 - t))
+    });
+
+    mesh
+}
+
+/// Capped cylinder along `Y` of `segments` around and `height_segments` along
+/// its side, `(segments + 1) * (height_segments + 1) + 2 * (segments + 2)` vertices
+pub fn cylinder(radius: f32, height: f32, segments: u32, height_segments: u32) -> MeshData {
+    let (segments, height_segments) = fit("cylinder", (segments.max(3), height_segments.max(1)), |s, h| (s + 1) * (h + 1) + 2 * (s + 2));
+    let mut mesh = MeshData::default();
+
+    let around = |column: u32| {
+        let phi = column as f32 / segments as f32 * TAU;
+        Vec3::new(phi.cos(), 0.0, -phi.sin())
+    };
+
+    mesh.grid(segments, height_segments, |column, row| {
+        let (s, t) = (column as f32 / segments as f32, row as f32 / height_segments as f32);
+        let normal = around(column);
+        (normal * radius + Vec3::Y * (t - 0.5) * height, normal, Vec2::new(s, 1.0 - t))
+    });
+
+    let cap_uv = |direction: Vec3| Vec2::new(0.5 + direction.x * 0.5, 0.5 + direction.z * 0.5);
+    let top = Vec3::Y * height * 0.5;
+
+    mesh.fan(top, Vec3::Y, (0..=segments).map(|c| (top + around(c) * radius, cap_uv(around(c)))));
+    mesh.fan(-top, Vec3::NEG_Y, (0..=segments).rev().map(|c| (around(c) * radius - top, cap_uv(around(c)))));
+
+    mesh
+}
+
+/// Ring around `Y` of `major_radius` with a tube of `minor_radius`,
+/// `(major_segments + 1) * (minor_segments + 1)` vertices
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> MeshData {
+    let (major_segments, minor_segments) = fit("torus", (major_segments.max(3), minor_segments.max(3)), |a, b| (a + 1) * (b + 1));
+    let mut mesh = MeshData::default();
+
+    mesh.grid(major_segments, minor_segments, |column, row| {
+        let (s, t) = (column as f32 / major_segments as f32, row as f32 / minor_segments as f32);
+        let (phi, psi) = (s * TAU, t * TAU);
+
+        let around = Vec3::new(phi.cos(), 0.0, -phi.sin());
+        let normal = around * psi.cos() + Vec3::Y * psi.sin();
+        (around * major_radius + normal * minor_radius, normal, Vec2::new(s, 1.0 - t))
+    });
+
+    mesh
+}
+
+/// Cylinder along `Y` of `height` between the centers of its two
+/// hemispherical caps of `rings` each, so the total height is `height + 2 * radius`.
+/// `(segments + 1) * (2 * rings + 2)` vertices.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = fit("capsule", (segments.max(3), rings.max(1)), |s, r| (s + 1) * (2 * r + 2));
+    let mut mesh = MeshData::default();
+
+    // Bottom hemisphere rows, then the top ones, the side spans the two equators
+    let rows = 2 * rings + 1;
+    let total = height + 2.0 * radius;
+
+    mesh.grid(segments, rows, |column, row| {
+        let s = column as f32 / segments as f32;
+        let (theta, offset) = match row <= rings {
+            true => (row as f32 / rings as f32 * FRAC_PI_2, -height * 0.5),
+            false => (FRAC_PI_2 + (row - rings - 1) as f32 / rings as f32 * FRAC_PI_2, height * 0.5),
+        };
+
+        let phi = s * TAU;
+        let normal = Vec3::new(theta.sin() * phi.cos(), -theta.cos(), -theta.sin() * phi.sin());
+        let position = normal * radius + Vec3::Y * offset;
+        (position, normal, Vec2::new(s, 1.0 - (position.y + total * 0.5) / total))
+    });
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> Vec<(&'static str, MeshData)> {
+        vec![
+            ("cube", cube(1.0, 2)),
+            ("cuboid", cuboid(Vec3::new(1.0, 2.0, 3.0), 1)),
+            ("sphere", sphere(1.0, 8, 4)),
+            ("plane", plane(Vec2::new(2.0, 1.0), 3)),
+            ("cylinder", cylinder(1.0, 2.0, 8, 2)),
+            ("torus", torus(1.0, 0.25, 8, 6)),
+            ("capsule", capsule(0.5, 1.0, 8, 2)),
+        ]
+    }
+
+    #[test]
+    fn vertex_and_index_counts() {
+        let counts = [
+            ("cube", 6 * 9, 6 * 4 * 6),
+            ("cuboid", 6 * 4, 6 * 6),
+            ("sphere", 9 * 5, 8 * 4 * 6),
+            ("plane", 16, 9 * 6),
+            ("cylinder", 9 * 3 + 2 * 10, 8 * 2 * 6 + 2 * 8 * 3),
+            ("torus", 9 * 7, 8 * 6 * 6),
+            ("capsule", 9 * 6, 8 * 5 * 6),
+        ];
+
+        for ((name, mesh), (_, vertices, indices)) in all().into_iter().zip(counts) {
+            assert_eq!(mesh.vertex_count(), vertices, "{}", name);
+            assert_eq!(mesh.normals.len(), vertices, "{}", name);
+            assert_eq!(mesh.uvs.len(), vertices, "{}", name);
+            assert_eq!(mesh.indices.len(), indices, "{}", name);
+            assert!(mesh.indices.iter().all(|&i| (i as usize) < vertices), "{}", name);
+        }
+    }
+
+    #[test]
+    fn triangles_wind_counter_clockwise_towards_the_normals() {
+        for (name, mesh) in all() {
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+                let (pa, pb, pc) = (Vec3::from(mesh.positions[a]), Vec3::from(mesh.positions[b]), Vec3::from(mesh.positions[c]));
+
+                let face = (pb - pa).cross(pc - pa);
+                // Collapsed triangles at the poles of spheres and capsules
+                if face.length() < 1e-6 {
+                    continue;
+                }
+
+                let normal = Vec3::from(mesh.normals[a]) + Vec3::from(mesh.normals[b]) + Vec3::from(mesh.normals[c]);
+                assert!(face.dot(normal) > 0.0, "{} triangle {:?}", name, triangle);
+            }
+        }
+    }
+
+    #[test]
+    fn normals_are_unit_length() {
+        for (name, mesh) in all() {
+            for normal in &mesh.normals {
+                assert!((Vec3::from(*normal).length() - 1.0).abs() < 1e-5, "{} {:?}", name, normal);
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_are_unit_and_orthogonal_to_the_normals() {
+        for (name, mesh) in all() {
+            let mesh = mesh.with_tangents();
+            let tangents = mesh.tangents.as_ref().unwrap();
+            assert_eq!(tangents.len(), mesh.vertex_count(), "{}", name);
+
+            for (tangent, normal) in tangents.iter().zip(&mesh.normals) {
+                let (t, n) = (Vec3::new(tangent[0], tangent[1], tangent[2]), Vec3::from(*normal));
+                assert!((t.length() - 1.0).abs() < 1e-4, "{} {:?}", name, tangent);
+                assert!(t.dot(n).abs() < 1e-4, "{} {:?} {:?}", name, tangent, normal);
+                assert!(tangent[3] == 1.0 || tangent[3] == -1.0, "{} {:?}", name, tangent);
+            }
+        }
+    }
+
+    #[test]
+    fn subdivisions_are_lowered_to_fit_u16_indices() {
+        assert_eq!(cube(1.0, 1000).vertex_count(), 6 * 104 * 104);
+        assert_eq!(plane(Vec2::ONE, 1000).vertex_count(), MAX_VERTICES);
+
+        for mesh in [sphere(1.0, 1000, 1000), cylinder(1.0, 1.0, 100_000, 10), torus(1.0, 0.5, 300, 300), capsule(1.0, 1.0, 500, 500)] {
+            assert!(mesh.vertex_count() <= MAX_VERTICES);
+            assert!(mesh.indices.iter().all(|&i| (i as usize) < mesh.vertex_count()));
+        }
+    }
+}
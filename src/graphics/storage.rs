@@ -0,0 +1,608 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+const EMPTY: u32 = u32::MAX;
+
+/// Ticks are compared every this many ticks and clamped to [`MAX_CHANGE_AGE`]
+pub const CHECK_TICK_THRESHOLD: u32 = 518_400_000;
+
+/// Oldest age a tick can have, older ticks are clamped by
+/// [`Components::check_change_ticks`] before wrapping around makes them look new
+pub const MAX_CHANGE_AGE: u32 = u32::MAX - (2 * CHECK_TICK_THRESHOLD - 1);
+
+/// `true` if `tick` is newer than `last`, both seen from `current`, so the
+/// comparison still holds after the tick counter wraps around
+pub fn is_newer_tick(tick: u32, last: u32, current: u32) -> bool {
+    current.wrapping_sub(tick) < current.wrapping_sub(last)
+}
+
+/// Moves `tick` forward to at most [`MAX_CHANGE_AGE`] before `current`
+pub fn clamp_tick(tick: &mut u32, current: u32) {
+    if current.wrapping_sub(*tick) > MAX_CHANGE_AGE {
+        *tick = current.wrapping_sub(MAX_CHANGE_AGE);
+    }
+}
+
+/// Generational handle of an entity owned by a [`GameWorld`](super::GameWorld).
+///
+/// The generation makes a handle of a despawned entity stale even after its
+/// index is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId {
+    index:      u32,
+    generation: u32,
+}
+
+impl EntityId {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// Allocator of entity ids, reuses the indices of despawned entities.
+#[derive(Debug, Default)]
+pub struct Entities {
+    generations:    Vec<u32>,
+    alive:          Vec<bool>,
+    free:           Vec<u32>,
+    len:            usize,
+}
+
+impl Entities {
+
+    pub fn alloc(&mut self) -> EntityId {
+        self.len += 1;
+
+        if let Some(index) = self.free.pop() {
+            self.alive[index as usize] = true;
+            return EntityId { index, generation: self.generations[index as usize] };
+        }
+
+        let index = self.generations.len() as u32;
+        self.generations.push(0);
+        self.alive.push(true);
+        EntityId { index, generation: 0 }
+    }
+
+    /// Returns `false` if the entity was already dead
+    pub fn free(&mut self, id: EntityId) -> bool {
+        if !self.is_alive(id) {
+            return false;
+        }
+
+        let index = id.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(id.index);
+        self.len -= 1;
+        true
+    }
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        let index = id.index as usize;
+        index < self.alive.len() && self.alive[index] && self.generations[index] == id.generation
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.alive.iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(move |(index, _)| EntityId { index: index as u32, generation: self.generations[index] })
+    }
+
+    /// Number of indices handed out so far, alive or not
+    pub(crate) fn indices(&self) -> usize {
+        self.alive.len()
+    }
+
+    /// The alive entity at `index`, if any
+    pub(crate) fn at(&self, index: usize) -> Option<EntityId> {
+        self.alive.get(index).copied()?.then(|| EntityId { index: index as u32, generation: self.generations[index] })
+    }
+}
+
+/// Densely packed values of one component type with O(1) lookup by entity.
+pub struct Column<T> {
+    dense:      Vec<T>,
+    entities:   Vec<EntityId>,
+    sparse:     Vec<u32>,
+    added:      Vec<u32>,
+    changed:    Vec<u32>,
+}
+
+impl<T> Default for Column<T> {
+    fn default() -> Self {
+        Self {
+            dense:      vec![],
+            entities:   vec![],
+            sparse:     vec![],
+            added:      vec![],
+            changed:    vec![],
+        }
+    }
+}
+
+impl<T> Column<T> {
+
+    pub(crate) fn slot(&self, entity: EntityId) -> Option<usize> {
+        let slot = *self.sparse.get(entity.index as usize)?;
+        if slot == EMPTY || self.entities[slot as usize] != entity {
+            return None;
+        }
+        Some(slot as usize)
+    }
+
+    /// Inserts or replaces the value of an entity, returning the old value.
+    /// A replaced value counts as changed, a new one as added and changed.
+    pub fn insert(&mut self, entity: EntityId, value: T, tick: u32) -> Option<T> {
+        if let Some(slot) = self.slot(entity) {
+            self.changed[slot] = tick;
+            return Some(std::mem::replace(&mut self.dense[slot], value));
+        }
+
+        let index = entity.index as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, EMPTY);
+        }
+
+        self.sparse[index] = self.dense.len() as u32;
+        self.dense.push(value);
+        self.entities.push(entity);
+        self.added.push(tick);
+        self.changed.push(tick);
+        None
+    }
+
+    pub fn remove(&mut self, entity: EntityId) -> Option<T> {
+        let slot = self.slot(entity)?;
+
+        self.sparse[entity.index as usize] = EMPTY;
+        self.entities.swap_remove(slot);
+        self.added.swap_remove(slot);
+        self.changed.swap_remove(slot);
+        let value = self.dense.swap_remove(slot);
+
+        // The last value moved into the hole
+        if let Some(moved) = self.entities.get(slot) {
+            self.sparse[moved.index as usize] = slot as u32;
+        }
+
+        Some(value)
+    }
+
+    pub fn get(&self, entity: EntityId) -> Option<&T> {
+        self.slot(entity).map(|slot| &self.dense[slot])
+    }
+
+    /// Mutable access without marking the value as changed
+    pub fn get_mut(&mut self, entity: EntityId) -> Option<&mut T> {
+        self.slot(entity).map(move |slot| &mut self.dense[slot])
+    }
+
+    /// Mutable access to the value and its changed tick
+    pub fn get_mut_with_tick(&mut self, entity: EntityId) -> Option<(&mut T, &mut u32)> {
+        let slot = self.slot(entity)?;
+        Some((&mut self.dense[slot], &mut self.changed[slot]))
+    }
+
+    /// Pointers to the first value and changed tick, indexed by [`slot`](Self::slot).
+    /// Lets a query hand out many `&mut T` without borrowing the column again.
+    pub(crate) fn as_mut_ptrs(&mut self) -> (*mut T, *mut u32) {
+        (self.dense.as_mut_ptr(), self.changed.as_mut_ptr())
+    }
+
+    pub fn added_tick(&self, entity: EntityId) -> Option<u32> {
+        self.slot(entity).map(|slot| self.added[slot])
+    }
+
+    pub fn changed_tick(&self, entity: EntityId) -> Option<u32> {
+        self.slot(entity).map(|slot| self.changed[slot])
+    }
+
+    pub fn contains(&self, entity: EntityId) -> bool {
+        self.slot(entity).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        self.entities.iter().copied().zip(self.dense.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut T)> {
+        self.entities.iter().copied().zip(self.dense.iter_mut())
+    }
+
+    /// Clamps the added and changed ticks, see [`clamp_tick`]
+    pub fn check_ticks(&mut self, current: u32) {
+        for tick in self.added.iter_mut().chain(self.changed.iter_mut()) {
+            clamp_tick(tick, current);
+        }
+    }
+}
+
+/// Type erased access to a [`Column`], used to despawn entities without
+/// knowing their component types.
+pub trait AnyColumn {
+    fn remove_entity(&mut self, entity: EntityId) -> Option<Box<dyn Any>>;
+    fn contains_entity(&self, entity: EntityId) -> bool;
+    fn check_ticks(&mut self, current: u32);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyColumn for Column<T> {
+    fn remove_entity(&mut self, entity: EntityId) -> Option<Box<dyn Any>> {
+        self.remove(entity).map(|value| Box::new(value) as Box<dyn Any>)
+    }
+
+    fn contains_entity(&self, entity: EntityId) -> bool {
+        self.contains(entity)
+    }
+
+    fn check_ticks(&mut self, current: u32) {
+        Column::check_ticks(self, current)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Entities that lost a `T`, by removal or despawn, since the last change tick.
+pub struct RemovedComponents<'w, T> {
+    removed:    std::slice::Iter<'w, (EntityId, u32)>,
+    last:       u32,
+    current:    u32,
+    marker:     PhantomData<T>,
+}
+
+impl<T> Iterator for RemovedComponents<'_, T> {
+    type Item = EntityId;
+
+    fn next(&mut self) -> Option<EntityId> {
+        let (last, current) = (self.last, self.current);
+        self.removed.find(|(_, tick)| is_newer_tick(*tick, last, current)).map(|(entity, _)| *entity)
+    }
+}
+
+/// One [`Column`] per component type, plus the ticks used for change detection.
+pub struct Components {
+    columns:            HashMap<TypeId, Box<dyn AnyColumn>>,
+    removed:            HashMap<TypeId, Vec<(EntityId, u32)>>,
+    change_tick:        u32,
+    last_change_tick:   u32,
+    last_check_tick:    u32,
+}
+
+impl Default for Components {
+    fn default() -> Self {
+        Self {
+            columns:            HashMap::new(),
+            removed:            HashMap::new(),
+            change_tick:        1,
+            last_change_tick:   0,
+            last_check_tick:    0,
+        }
+    }
+}
+
+impl Components {
+
+    /// Tick stamped on components added or changed right now
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick
+    }
+
+    /// Changes newer than this tick count as `Added` / `Changed`
+    pub fn last_change_tick(&self) -> u32 {
+        self.last_change_tick
+    }
+
+    pub fn set_last_change_tick(&mut self, tick: u32) {
+        self.last_change_tick = tick;
+    }
+
+    /// Starts a new tick and returns it
+    pub fn increment_change_tick(&mut self) -> u32 {
+        self.change_tick = self.change_tick.wrapping_add(1);
+        self.change_tick
+    }
+
+    /// Clamps every stored tick older than [`MAX_CHANGE_AGE`] once every
+    /// [`CHECK_TICK_THRESHOLD`] ticks, so old changes never look new once the
+    /// counter wraps around. Returns `true` when the ticks were checked.
+    pub fn check_change_ticks(&mut self) -> bool {
+        let current = self.change_tick;
+        if current.wrapping_sub(self.last_check_tick) < CHECK_TICK_THRESHOLD {
+            return false;
+        }
+
+        for column in self.columns.values_mut() {
+            column.check_ticks(current);
+        }
+        for removed in self.removed.values_mut() {
+            for (_, tick) in removed.iter_mut() {
+                clamp_tick(tick, current);
+            }
+        }
+        clamp_tick(&mut self.last_change_tick, current);
+
+        self.last_check_tick = current;
+        true
+    }
+
+    /// Ends a frame: later reads only see changes made after this call.
+    /// Removals are kept for one more frame so early stages of the next
+    /// frame still see removals from late stages of this one.
+    pub fn clear_trackers(&mut self) {
+        let (previous, current) = (self.last_change_tick, self.change_tick);
+        for removed in self.removed.values_mut() {
+            removed.retain(|(_, tick)| is_newer_tick(*tick, previous, current));
+        }
+
+        self.last_change_tick = self.change_tick;
+        self.change_tick = self.change_tick.wrapping_add(1);
+    }
+
+    /// Entities that lost a `T` since the last change tick
+    pub fn removed<T: 'static>(&self) -> RemovedComponents<'_, T> {
+        RemovedComponents {
+            removed:    self.removed.get(&TypeId::of::<T>()).map_or(&[][..], |r| r.as_slice()).iter(),
+            last:       self.last_change_tick,
+            current:    self.change_tick,
+            marker:     PhantomData,
+        }
+    }
+
+    pub fn column<T: 'static>(&self) -> Option<&Column<T>> {
+        self.columns
+            .get(&TypeId::of::<T>())
+            .map(|c| c.as_any().downcast_ref::<Column<T>>().unwrap())
+    }
+
+    pub fn column_mut<T: 'static>(&mut self) -> Option<&mut Column<T>> {
+        self.columns
+            .get_mut(&TypeId::of::<T>())
+            .map(|c| c.as_any_mut().downcast_mut::<Column<T>>().unwrap())
+    }
+
+    fn column_or_default<T: 'static>(&mut self) -> &mut Column<T> {
+        self.columns
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Column::<T>::default()))
+            .as_any_mut()
+            .downcast_mut::<Column<T>>()
+            .unwrap()
+    }
+
+    pub fn insert<T: 'static>(&mut self, entity: EntityId, component: T) -> Option<T> {
+        let tick = self.change_tick;
+        self.column_or_default::<T>().insert(entity, component, tick)
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: EntityId) -> Option<T> {
+        let value = self.column_mut::<T>()?.remove(entity)?;
+        self.removed.entry(TypeId::of::<T>()).or_default().push((entity, self.change_tick));
+        Some(value)
+    }
+
+    pub fn get<T: 'static>(&self, entity: EntityId) -> Option<&T> {
+        self.column::<T>()?.get(entity)
+    }
+
+    /// Marks the component as changed, whether or not it is written
+    pub fn get_mut<T: 'static>(&mut self, entity: EntityId) -> Option<&mut T> {
+        let tick = self.change_tick;
+        let (value, changed) = self.column_mut::<T>()?.get_mut_with_tick(entity)?;
+        *changed = tick;
+        Some(value)
+    }
+
+    pub fn is_added<T: 'static>(&self, entity: EntityId) -> bool {
+        self.column::<T>()
+            .and_then(|c| c.added_tick(entity))
+            .is_some_and(|tick| is_newer_tick(tick, self.last_change_tick, self.change_tick))
+    }
+
+    pub fn is_changed<T: 'static>(&self, entity: EntityId) -> bool {
+        self.column::<T>()
+            .and_then(|c| c.changed_tick(entity))
+            .is_some_and(|tick| is_newer_tick(tick, self.last_change_tick, self.change_tick))
+    }
+
+    pub fn contains<T: 'static>(&self, entity: EntityId) -> bool {
+        self.column::<T>().is_some_and(|c| c.contains(entity))
+    }
+
+    /// Removes every component of the entity and returns them
+    pub fn remove_all(&mut self, entity: EntityId) -> Vec<Box<dyn Any>> {
+        let mut removed = vec![];
+        for (type_id, column) in self.columns.iter_mut() {
+            if let Some(value) = column.remove_entity(entity) {
+                self.removed.entry(*type_id).or_default().push((entity, self.change_tick));
+                removed.push(value);
+            }
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freed_index_is_reused_with_a_new_generation() {
+        let mut entities = Entities::default();
+        let a = entities.alloc();
+        let b = entities.alloc();
+
+        assert!(entities.free(a));
+        assert!(!entities.free(a));
+
+        let c = entities.alloc();
+        assert_eq!(c.index(), a.index());
+        assert_eq!(c.generation(), a.generation() + 1);
+
+        assert!(!entities.is_alive(a));
+        assert!(entities.is_alive(b));
+        assert!(entities.is_alive(c));
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities.iter().collect::<Vec<_>>(), vec![c, b]);
+    }
+
+    #[test]
+    fn stale_id_is_rejected_by_a_column() {
+        let mut entities = Entities::default();
+        let mut column = Column::default();
+
+        let stale = entities.alloc();
+        column.insert(stale, 1, 1);
+        column.remove(stale);
+        entities.free(stale);
+
+        let reused = entities.alloc();
+        column.insert(reused, 2, 1);
+
+        assert_eq!(column.get(stale), None);
+        assert_eq!(column.remove(stale), None);
+        assert_eq!(column.get(reused), Some(&2));
+    }
+
+    #[test]
+    fn swap_remove_keeps_the_moved_entity_reachable() {
+        let mut entities = Entities::default();
+        let ids: Vec<EntityId> = (0..4).map(|_| entities.alloc()).collect();
+
+        let mut column = Column::default();
+        for (i, id) in ids.iter().enumerate() {
+            column.insert(*id, i, 1);
+        }
+
+        // The last entity moves into the first slot
+        assert_eq!(column.remove(ids[0]), Some(0));
+        assert_eq!(column.entities(), &[ids[3], ids[1], ids[2]]);
+        assert_eq!(column.get(ids[3]), Some(&3));
+        assert_eq!(column.slot(ids[3]), Some(0));
+
+        // Removing the last slot moves nothing
+        assert_eq!(column.remove(ids[2]), Some(2));
+        assert_eq!(column.get(ids[1]), Some(&1));
+        assert_eq!(column.get(ids[3]), Some(&3));
+        assert_eq!(column.get(ids[0]), None);
+        assert_eq!(column.len(), 2);
+    }
+
+    /// Jumps `ticks` ticks ahead as if that many frames ran without changes
+    fn advance(components: &mut Components, ticks: u32) {
+        components.change_tick = components.change_tick.wrapping_add(ticks);
+        components.last_change_tick = components.change_tick.wrapping_sub(1);
+    }
+
+    #[test]
+    fn added_and_changed_across_tick_wrap() {
+        let mut entities = Entities::default();
+        let (a, b) = (entities.alloc(), entities.alloc());
+
+        let mut components = Components {
+            change_tick:        u32::MAX - 1,
+            last_change_tick:   u32::MAX - 2,
+            ..Components::default()
+        };
+
+        components.insert(a, 1u32);
+        assert!(components.is_added::<u32>(a));
+
+        components.clear_trackers();
+        components.clear_trackers();
+        assert_eq!(components.change_tick(), 0);
+        assert!(!components.is_added::<u32>(a));
+        assert!(!components.is_changed::<u32>(a));
+
+        components.insert(b, 2u32);
+        *components.get_mut::<u32>(a).unwrap() += 1;
+        assert!(components.is_added::<u32>(b));
+        assert!(!components.is_added::<u32>(a));
+        assert!(components.is_changed::<u32>(a));
+
+        components.clear_trackers();
+        assert!(!components.is_added::<u32>(b));
+        assert!(!components.is_changed::<u32>(a));
+    }
+
+    #[test]
+    fn old_ticks_are_clamped() {
+        let mut entities = Entities::default();
+        let a = entities.alloc();
+
+        let mut components = Components::default();
+        components.insert(a, 1u32);
+        let added = components.column::<u32>().unwrap().added_tick(a).unwrap();
+
+        advance(&mut components, CHECK_TICK_THRESHOLD - 2);
+        assert!(!components.check_change_ticks());
+
+        advance(&mut components, MAX_CHANGE_AGE);
+        assert!(components.check_change_ticks());
+        let current = components.change_tick();
+        let clamped = components.column::<u32>().unwrap().added_tick(a).unwrap();
+        assert_ne!(clamped, added);
+        assert_eq!(current.wrapping_sub(clamped), MAX_CHANGE_AGE);
+
+        let mut young = current.wrapping_sub(10);
+        clamp_tick(&mut young, current);
+        assert_eq!(young, current.wrapping_sub(10));
+    }
+
+    #[test]
+    fn old_component_never_looks_new_after_a_full_wrap() {
+        let mut entities = Entities::default();
+        let a = entities.alloc();
+
+        let mut components = Components::default();
+        components.insert(a, 1u32);
+        let added = components.change_tick();
+
+        // Checked every threshold like `Schedule::run_frame`, until the
+        // counter comes back to the tick the component was added at
+        let mut remaining = 1u64 << 32;
+        while remaining > 0 {
+            let step = remaining.min(CHECK_TICK_THRESHOLD as u64) as u32;
+            advance(&mut components, step);
+            components.check_change_ticks();
+            remaining -= step as u64;
+        }
+
+        assert_eq!(components.change_tick(), added);
+        assert!(!components.is_added::<u32>(a));
+        assert!(!components.is_changed::<u32>(a));
+    }
+}
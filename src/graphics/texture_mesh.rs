@@ -1,15 +1,15 @@
-use super::{Entity, Vertex3DTexture};
-
-pub struct ComponentTextureMesh {
-    pub vertex: Vec<Vertex3DTexture>
-}
-
-pub trait SystemTextureMesh {
-    fn add_texture_mesh(&mut self);
-}
-
-impl SystemTextureMesh for Entity<'_> {
-    fn add_texture_mesh(&mut self) {
-
-    }
+use super::{Entity, Vertex3DTexture};
+
+pub struct ComponentTextureMesh {
+    pub vertex: Vec<Vertex3DTexture>
+}
+
+pub trait SystemTextureMesh {
+    fn add_texture_mesh(&mut self);
+}
+
+impl SystemTextureMesh for Entity<'_, '_> {
+    fn add_texture_mesh(&mut self) {
+
+    }
 }
\ No newline at end of file
@@ -0,0 +1,305 @@
+use std::collections::HashSet;
+
+use super::{id, uniform_bind_group, Changed, EntityId, GameWorld, Id, Stage, System, With, Without};
+use crate::{to_gpu, Mat3, Mat4, Quat, Vec3};
+
+/// Position, rotation and scale of an entity relative to its [`Parent`],
+/// or to the world for root entities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation:    Vec3,
+    pub rotation:       Quat,
+    pub scale:          Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+
+    pub const IDENTITY: Self = Self {
+        translation:    Vec3::ZERO,
+        rotation:       Quat::IDENTITY,
+        scale:          Vec3::ONE,
+    };
+
+    pub fn from_xyz(x: f32, y: f32, z: f32) -> Self {
+        Self::from_translation(Vec3::new(x, y, z))
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self { translation, ..Self::IDENTITY }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self { rotation, ..Self::IDENTITY }
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self { scale, ..Self::IDENTITY }
+    }
+
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Self { translation, rotation, scale }
+    }
+
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Local to parent matrix: scale, then rotation, then translation
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Direction of the local `-Z` axis
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        self.translation += offset;
+    }
+
+    /// Rotates around an axis of the parent space
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.rotation = rotation * self.rotation;
+    }
+
+    /// Rotates around one of the entity's own axes
+    pub fn rotate_local(&mut self, rotation: Quat) {
+        self.rotation *= rotation;
+    }
+
+    /// Turns `-Z` towards `target`, keeping `up` as close to `Y` as possible
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let forward = (target - self.translation).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        self.rotation = Quat::from_mat3(&Mat3::from_cols(right, up, -forward));
+    }
+
+    pub fn looking_at(mut self, target: Vec3, up: Vec3) -> Self {
+        self.look_at(target, up);
+        self
+    }
+
+    /// `self` applied after `child`, i.e. the child expressed in this transform's parent space
+    pub fn mul_transform(&self, child: &Transform) -> Transform {
+        Transform::from_matrix(self.compute_matrix() * child.compute_matrix())
+    }
+}
+
+/// World matrix of an entity, written by the transform propagation system
+/// from its [`Transform`] and the transforms of its ancestors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(pub Mat4);
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self(Mat4::IDENTITY)
+    }
+}
+
+impl GlobalTransform {
+
+    pub fn matrix(&self) -> Mat4 {
+        self.0
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        self.0.w_axis.truncate()
+    }
+
+    pub fn to_transform(&self) -> Transform {
+        Transform::from_matrix(self.0)
+    }
+}
+
+/// Parent of an entity, set with [`GameWorld::set_parent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub EntityId);
+
+/// Children of an entity, kept in sync with their [`Parent`] by
+/// [`GameWorld::set_parent`] and [`GameWorld::remove_parent`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub Vec<EntityId>);
+
+/// Per-entity uniform buffer holding the world matrix for the mesh shaders
+#[derive(Debug)]
+pub struct ComponentModel {
+    pub buffer:     Id,
+    pub bind_group: Id,
+}
+
+impl< 'p> GameWorld< 'p> {
+
+    /// Makes `child` a child of `parent`, detaching it from its previous parent
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) {
+        if child == parent || !self.is_alive(child) || !self.is_alive(parent) {
+            return;
+        }
+
+        self.remove_parent(child);
+        self.add_component(child, Parent(parent));
+
+        match self.get_mut_component::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => self.add_component(parent, Children(vec![child])),
+        }
+    }
+
+    /// Makes `child` a root entity again
+    pub fn remove_parent(&mut self, child: EntityId) {
+        let Some(Parent(parent)) = self.remove_component::<Parent>(child) else {
+            return;
+        };
+
+        if let Some(children) = self.get_mut_component::<Children>(parent) {
+            children.0.retain(|c| *c != child);
+        }
+    }
+
+    /// Despawns the entity and all of its descendants
+    pub fn despawn_recursive(&mut self, id: EntityId) {
+        self.remove_parent(id);
+
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(children) = self.remove_component::<Children>(id) {
+                stack.extend(children.0);
+            }
+            self.despawn(id);
+        }
+    }
+
+    /// Writes the [`GlobalTransform`] of every entity with a [`Transform`],
+    /// parents before children. Only matrices that moved are marked changed.
+    /// Roots are the entities without a [`Parent`] holding a [`Transform`] or
+    /// [`Children`], and entities no longer reached from one lose their
+    /// [`GlobalTransform`] and model uniform.
+    pub fn propagate_transforms(&mut self) {
+        let mut globals = vec![];
+        let mut stack: Vec<(EntityId, Mat4)> = vec![];
+
+        for (id, transform, children) in self.query_filtered::<(EntityId, Option<&Transform>, Option<&Children>), Without<Parent>>() {
+            if transform.is_some() || children.is_some() {
+                stack.push((id, transform.map_or(Mat4::IDENTITY, |t| t.compute_matrix())));
+            }
+        }
+
+        while let Some((id, global)) = stack.pop() {
+            globals.push((id, global));
+
+            let Some(children) = self.get_component::<Children>(id) else {
+                continue;
+            };
+
+            for &child in &children.0 {
+                if !self.is_alive(child) {
+                    continue;
+                }
+                // A child without a transform sits exactly on its parent
+                let local = self.get_component::<Transform>(child).map_or(Mat4::IDENTITY, |t| t.compute_matrix());
+                stack.push((child, global * local));
+            }
+        }
+
+        let reached: HashSet<EntityId> = globals.iter().map(|(id, _)| *id).collect();
+        let orphans: Vec<EntityId> = self
+            .query_filtered::<EntityId, With<GlobalTransform>>()
+            .filter(|id| !reached.contains(id))
+            .collect();
+
+        for id in orphans {
+            self.remove_component::<GlobalTransform>(id);
+            self.remove_component::<ComponentModel>(id);
+        }
+
+        for (id, global) in globals {
+            match self.components.get::<GlobalTransform>(id) {
+                Some(current) if current.0 == global => (),
+                Some(_) => *self.get_mut_component::<GlobalTransform>(id).unwrap() = GlobalTransform(global),
+                None => self.add_component(id, GlobalTransform(global)),
+            }
+        }
+    }
+
+    /// Creates the model uniform of new entities with a [`GlobalTransform`]
+    /// and re-uploads the ones whose matrix changed
+    pub fn upload_model_uniforms(&mut self) {
+        let created: Vec<(EntityId, Mat4)> = self
+            .query_filtered::<(EntityId, &GlobalTransform), Without<ComponentModel>>()
+            .map(|(id, global)| (id, global.0))
+            .collect();
+
+        let changed: Vec<(Id, Mat4)> = self
+            .query_filtered::<(&GlobalTransform, &ComponentModel), Changed<GlobalTransform>>()
+            .map(|(global, model)| (model.buffer, global.0))
+            .collect();
+
+        let models: Vec<(EntityId, ComponentModel)> = {
+            let mut res = self.resource.borrow_mut();
+            let res = &mut *res;
+
+            for (buffer, matrix) in changed {
+                res.ctx.queue.write_buffer(&res.uniform_buffer[&buffer], 0, bytemuck::cast_slice(&to_gpu(matrix)));
+            }
+
+            created
+                .into_iter()
+                .map(|(entity, matrix)| {
+                    let (buffer, bind_group) = uniform_bind_group(
+                        &res.ctx.device,
+                        &res.model_bind_group_layout,
+                        "Model Uniform",
+                        bytemuck::cast_slice(&to_gpu(matrix)),
+                    );
+
+                    let model = ComponentModel { buffer: id(), bind_group: id() };
+                    res.uniform_buffer.insert(model.buffer, buffer);
+                    res.bind_group.insert(model.bind_group, bind_group);
+                    (entity, model)
+                })
+                .collect()
+        };
+
+        for (entity, model) in models {
+            self.add_component(entity, model);
+        }
+    }
+
+    /// Registers transform propagation and the model uniform upload in
+    /// [`Stage::PostUpdate`], done once by [`GameWorld::new`]
+    pub(crate) fn add_transform_systems(&mut self) {
+        self.add_system(Stage::PostUpdate, System::new("transform_propagate", |world| world.propagate_transforms())
+            .query::<(&Transform, &Parent, &Children, &mut GlobalTransform, &mut ComponentModel)>());
+
+        self.add_system(Stage::PostUpdate, System::new("model_upload", |world| world.upload_model_uniforms())
+            .query::<(&GlobalTransform, &mut ComponentModel)>()
+            .after("transform_propagate"));
+    }
+}
@@ -1,125 +1,207 @@
-#![allow(warnings)]
-
-use wgpu::VertexAttribute;
-use wgpu::VertexBufferLayout;
-use wgpu::VertexStepMode;
-use bytemuck::{Pod, Zeroable};
-
-
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
-pub struct Vertex3D {
-    pub pos:   [f32; 3],
-    pub color: [f32; 3],
-}
-
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
-pub struct Vertex3DTexture {
-    pub pos:        [f32; 3],
-    pub tex_pos:    [f32; 3],
-}
-
-pub trait WebGPUType {
-    fn layout(&self) -> VertexBufferLayout<'static>;
-    fn bytes(&self)  -> &[u8];
-}
-
-impl WebGPUType for Vec<Vertex3D> {
-
-    fn bytes(&self)  -> &[u8] {
-        bytemuck::cast_slice(self)
-    }
-
-    fn layout(&self) -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride:   24,
-            step_mode:      VertexStepMode::Vertex,
-            attributes:     &[
-
-                VertexAttribute {
-                    format:             wgpu::VertexFormat::Float32x3,
-                    offset:             0 as u64,
-                    shader_location:    0,
-                },
-
-                VertexAttribute {
-                    format:             wgpu::VertexFormat::Float32x3,
-                    offset:             12,
-                    shader_location:    1,
-                }
-
-            ],
-        }
-    }
-}
-
-impl WebGPUType for Vec<Vertex3DTexture> {
-
-    fn bytes(&self)  -> &[u8] {
-        bytemuck::cast_slice(self)
-    }
-
-    fn layout(&self) -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride:   24,
-            step_mode:      VertexStepMode::Vertex,
-            attributes:     &[
-
-                VertexAttribute {
-                    format:             wgpu::VertexFormat::Float32x3,
-                    offset:             0 as u64,
-                    shader_location:    0,
-                },
-
-                VertexAttribute {
-                    format:             wgpu::VertexFormat::Float32x3,
-                    offset:             12,
-                    shader_location:    1,
-                }
-
-            ],
-        }
-    }
-}
-
-
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
-pub struct Camera {
-    pub matrix:   [[f32; 4]; 4],
-}
-
-impl WebGPUType for Camera {
-
-    fn bytes(&self) -> &[u8] {
-        unsafe {
-            std::slice::from_raw_parts(
-                self as *const _ as *const u8,
-                std::mem::size_of::<Camera>(),
-            )
-        }
-    }
-
-    fn layout(&self) -> VertexBufferLayout<'static> {
-        VertexBufferLayout {
-            array_stride:   24,
-            step_mode:      VertexStepMode::Vertex,
-            attributes:     &[
-
-                VertexAttribute {
-                    format:             wgpu::VertexFormat::Float32x3,
-                    offset:             0 as u64,
-                    shader_location:    0,
-                },
-
-                VertexAttribute {
-                    format:             wgpu::VertexFormat::Float32x3,
-                    offset:             12,
-                    shader_location:    1,
-                }
-
-            ],
-        }
-    }
+#![allow(warnings)]
+
+use wgpu::VertexAttribute;
+use wgpu::VertexBufferLayout;
+use wgpu::VertexStepMode;
+use bytemuck::{Pod, Zeroable};
+
+use crate::{from_gpu, to_gpu, Mat4};
+
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct Vertex3D {
+    pub pos:   [f32; 3],
+    pub color: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct Vertex3DTexture {
+    pub pos:        [f32; 3],
+    pub tex_pos:    [f32; 3],
+}
+
+pub trait WebGPUType {
+    fn layout(&self) -> VertexBufferLayout<'static>;
+    fn bytes(&self)  -> &[u8];
+}
+
+impl WebGPUType for Vec<Vertex3D> {
+
+    fn bytes(&self)  -> &[u8] {
+        bytemuck::cast_slice(self)
+    }
+
+    fn layout(&self) -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride:   24,
+            step_mode:      VertexStepMode::Vertex,
+            attributes:     &[
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x3,
+                    offset:             0 as u64,
+                    shader_location:    0,
+                },
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x3,
+                    offset:             12,
+                    shader_location:    1,
+                }
+
+            ],
+        }
+    }
+}
+
+impl WebGPUType for Vec<Vertex3DTexture> {
+
+    fn bytes(&self)  -> &[u8] {
+        bytemuck::cast_slice(self)
+    }
+
+    fn layout(&self) -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride:   24,
+            step_mode:      VertexStepMode::Vertex,
+            attributes:     &[
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x3,
+                    offset:             0 as u64,
+                    shader_location:    0,
+                },
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x3,
+                    offset:             12,
+                    shader_location:    1,
+                }
+
+            ],
+        }
+    }
+}
+
+
+/// Per-instance vertex data of the instanced mesh pipeline: the world matrix
+/// columns at locations 4 to 7 and a color multiplied with the vertex color at 8.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct InstanceRaw {
+    pub model:  [[f32; 4]; 4],
+    pub color:  [f32; 4],
+}
+
+impl InstanceRaw {
+    pub fn new(model: Mat4, color: [f32; 4]) -> Self {
+        Self { model: to_gpu(model), color }
+    }
+}
+
+impl WebGPUType for Vec<InstanceRaw> {
+
+    fn bytes(&self)  -> &[u8] {
+        bytemuck::cast_slice(self)
+    }
+
+    fn layout(&self) -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride:   80,
+            step_mode:      VertexStepMode::Instance,
+            attributes:     &[
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x4,
+                    offset:             0,
+                    shader_location:    4,
+                },
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x4,
+                    offset:             16,
+                    shader_location:    5,
+                },
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x4,
+                    offset:             32,
+                    shader_location:    6,
+                },
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x4,
+                    offset:             48,
+                    shader_location:    7,
+                },
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x4,
+                    offset:             64,
+                    shader_location:    8,
+                }
+
+            ],
+        }
+    }
+}
+
+/// View-projection uniform of a [`Camera`](super::Camera). `view_proj` holds
+/// the columns of the matrix, see [`math`](crate::math) for the convention.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct CameraUniform {
+    pub view_proj:  [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    pub fn new(view_proj: Mat4) -> Self {
+        Self { view_proj: to_gpu(view_proj) }
+    }
+
+    pub fn view_proj(&self) -> Mat4 {
+        from_gpu(self.view_proj)
+    }
+}
+
+impl From<Mat4> for CameraUniform {
+    fn from(view_proj: Mat4) -> Self {
+        Self::new(view_proj)
+    }
+}
+
+impl WebGPUType for CameraUniform {
+
+    fn bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(
+                self as *const _ as *const u8,
+                std::mem::size_of::<CameraUniform>(),
+            )
+        }
+    }
+
+    fn layout(&self) -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride:   24,
+            step_mode:      VertexStepMode::Vertex,
+            attributes:     &[
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x3,
+                    offset:             0 as u64,
+                    shader_location:    0,
+                },
+
+                VertexAttribute {
+                    format:             wgpu::VertexFormat::Float32x3,
+                    offset:             12,
+                    shader_location:    1,
+                }
+
+            ],
+        }
+    }
 }
\ No newline at end of file
@@ -1,45 +1,55 @@
-use wgpu::util::BufferInitDescriptor;
-use wgpu::util::DeviceExt;
-use wgpu::BufferUsages;
-use wgpu::ShaderStages;
-
-use super::id;
-use super::Id;
-use super::{Entity, WebGPUType};
-
-#[derive(Debug)]
-pub struct ComponentUniform {
-    pub buffer:     Id,
-    pub visible:    ShaderStages,
-}
-
-impl ComponentUniform{
-    fn new<T: WebGPUType>(entity: &Entity, vis: ShaderStages, uniform: T) -> Self {
-
-        let mut res = entity.game_resource.borrow_mut();
-
-        let uniform = res.ctx.device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: uniform.bytes(),
-            usage: BufferUsages::UNIFORM | BufferUsages::UNIFORM
-        });
-
-        let id = id();
-        res.uniform_buffer.insert(id, uniform);
-
-        Self{
-            buffer: id,
-            visible: vis
-        }
-    }
-}
-
-pub trait SystemUniform {
-    fn add_uniform<T: WebGPUType>(&mut self, vis: ShaderStages, uniform: T);
-}
-
-impl SystemUniform for Entity<'_> {
-    fn add_uniform<T: WebGPUType>(&mut self, vis: ShaderStages, uniform: T) {
-        self.add_component(ComponentUniform::new(self, vis, uniform));
-    }
+use wgpu::util::BufferInitDescriptor;
+use wgpu::util::DeviceExt;
+use wgpu::BufferUsages;
+use wgpu::ShaderStages;
+
+use super::id;
+use super::Id;
+use super::{Entity, WebGPUType};
+
+#[derive(Debug)]
+pub struct Uniform {
+    pub buffer:     Id,
+    pub visible:    ShaderStages,
+}
+
+/// Uniform buffers of an entity, bound in order starting at binding 0
+#[derive(Debug, Default)]
+pub struct ComponentUniform {
+    pub uniforms:   Vec<Uniform>,
+}
+
+impl Uniform {
+    fn new<T: WebGPUType>(entity: &Entity, vis: ShaderStages, uniform: T) -> Self {
+
+        let mut res = entity.game_resource().borrow_mut();
+
+        let uniform = res.ctx.device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: uniform.bytes(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST
+        });
+
+        let id = id();
+        res.uniform_buffer.insert(id, uniform);
+
+        Self{
+            buffer: id,
+            visible: vis
+        }
+    }
+}
+
+pub trait SystemUniform {
+    fn add_uniform<T: WebGPUType>(&mut self, vis: ShaderStages, uniform: T);
+}
+
+impl SystemUniform for Entity<'_, '_> {
+    fn add_uniform<T: WebGPUType>(&mut self, vis: ShaderStages, uniform: T) {
+        let uniform = Uniform::new(self, vis, uniform);
+        match self.get_mut_component::<ComponentUniform>() {
+            Some(component) => component.uniforms.push(uniform),
+            None => self.add_component(ComponentUniform { uniforms: vec![uniform] }),
+        }
+    }
 }
\ No newline at end of file
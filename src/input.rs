@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, ElementState, MouseScrollDelta, TouchPhase, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+pub use winit::event::MouseButton;
+
+/// Pressed state of a set of buttons, with the edges of the current frame.
+#[derive(Debug, Clone)]
+pub struct ButtonInput<T: Copy + Eq + Hash> {
+    pressed:        HashSet<T>,
+    just_pressed:   HashSet<T>,
+    just_released:  HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> Default for ButtonInput<T> {
+    fn default() -> Self {
+        Self {
+            pressed:        HashSet::new(),
+            just_pressed:   HashSet::new(),
+            just_released:  HashSet::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> ButtonInput<T> {
+
+    pub fn press(&mut self, button: T) {
+        // Key repeat sends more presses, only the first one is an edge
+        if self.pressed.insert(button) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: T) {
+        if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        for button in self.pressed.drain() {
+            self.just_released.insert(button);
+        }
+    }
+
+    pub fn pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    pub fn any_pressed(&self, buttons: impl IntoIterator<Item = T>) -> bool {
+        buttons.into_iter().any(|b| self.pressed(b))
+    }
+
+    pub fn get_pressed(&self) -> impl Iterator<Item = &T> {
+        self.pressed.iter()
+    }
+
+    pub fn get_just_pressed(&self) -> impl Iterator<Item = &T> {
+        self.just_pressed.iter()
+    }
+
+    pub fn get_just_released(&self) -> impl Iterator<Item = &T> {
+        self.just_released.iter()
+    }
+
+    /// Forgets the edges, called once at the end of every frame
+    pub fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+/// Wheel pixels counted as one line, touchpads scroll in pixels
+pub(crate) const PIXELS_PER_LINE: f32 = 40.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchPoint {
+    pub id:         u64,
+    pub phase:      TouchPhase,
+    pub start:      PhysicalPosition<f64>,
+    pub position:   PhysicalPosition<f64>,
+    /// Movement since the previous frame
+    pub delta:      (f64, f64),
+}
+
+/// Keyboard, mouse and touch state fed from winit events.
+///
+/// The [`App`](crate::App) runner feeds it before the update callbacks and
+/// clears the per-frame edges after rendering, so gameplay code only polls it.
+#[derive(Debug, Clone, Default)]
+pub struct Input {
+    pub keys:           ButtonInput<KeyCode>,
+    pub mouse_buttons:  ButtonInput<MouseButton>,
+    window_size:        PhysicalSize<u32>,
+    cursor:             Option<PhysicalPosition<f64>>,
+    cursor_delta:       (f64, f64),
+    mouse_motion:       (f64, f64),
+    scroll_lines:       (f32, f32),
+    scroll_pixels:      (f64, f64),
+    touches:            HashMap<u64, TouchPoint>,
+    just_started:       Vec<u64>,
+    just_ended:         Vec<TouchPoint>,
+    unfocused:          bool,
+}
+
+impl Input {
+
+    pub fn process_window_event(&mut self, event: &WindowEvent) {
+        match event {
+
+            WindowEvent::Resized(size) => {
+                self.window_size = *size;
+            }
+
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    match event.state {
+                        ElementState::Pressed => self.keys.press(code),
+                        ElementState::Released => self.keys.release(code),
+                    }
+                }
+            }
+
+            WindowEvent::MouseInput { state, button, .. } => {
+                match state {
+                    ElementState::Pressed => self.mouse_buttons.press(*button),
+                    ElementState::Released => self.mouse_buttons.release(*button),
+                }
+            }
+
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(last) = self.cursor {
+                    self.cursor_delta.0 += position.x - last.x;
+                    self.cursor_delta.1 += position.y - last.y;
+                }
+                self.cursor = Some(*position);
+            }
+
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+            }
+
+            WindowEvent::MouseWheel { delta, .. } => {
+                match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        self.scroll_lines.0 += x;
+                        self.scroll_lines.1 += y;
+                    }
+                    MouseScrollDelta::PixelDelta(p) => {
+                        self.scroll_pixels.0 += p.x;
+                        self.scroll_pixels.1 += p.y;
+                    }
+                }
+            }
+
+            WindowEvent::Touch(touch) => {
+                match touch.phase {
+                    TouchPhase::Started => {
+                        self.touches.insert(touch.id, TouchPoint {
+                            id:         touch.id,
+                            phase:      touch.phase,
+                            start:      touch.location,
+                            position:   touch.location,
+                            delta:      (0.0, 0.0),
+                        });
+                        self.just_started.push(touch.id);
+                    }
+                    TouchPhase::Moved => {
+                        if let Some(point) = self.touches.get_mut(&touch.id) {
+                            point.delta.0 += touch.location.x - point.position.x;
+                            point.delta.1 += touch.location.y - point.position.y;
+                            point.position = touch.location;
+                            point.phase = touch.phase;
+                        }
+                    }
+                    TouchPhase::Ended | TouchPhase::Cancelled => {
+                        if let Some(mut point) = self.touches.remove(&touch.id) {
+                            point.position = touch.location;
+                            point.phase = touch.phase;
+                            self.just_ended.push(point);
+                        }
+                    }
+                }
+            }
+
+            // Keys held while the window loses focus never get a release event
+            WindowEvent::Focused(false) => {
+                self.keys.release_all();
+                self.mouse_buttons.release_all();
+                self.unfocused = true;
+            }
+
+            WindowEvent::Focused(true) => {
+                self.unfocused = false;
+            }
+
+            _ => ()
+        }
+    }
+
+    /// Raw mouse motion, which keeps working while the pointer is locked.
+    pub fn process_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.mouse_motion.0 += delta.0;
+            self.mouse_motion.1 += delta.1;
+        }
+    }
+
+    /// Clears per-frame state: button edges, deltas and ended touches.
+    pub fn end_frame(&mut self) {
+        self.keys.clear();
+        self.mouse_buttons.clear();
+        self.cursor_delta = (0.0, 0.0);
+        self.mouse_motion = (0.0, 0.0);
+        self.scroll_lines = (0.0, 0.0);
+        self.scroll_pixels = (0.0, 0.0);
+        self.just_started.clear();
+        self.just_ended.clear();
+
+        for point in self.touches.values_mut() {
+            point.delta = (0.0, 0.0);
+        }
+    }
+
+    pub fn set_window_size(&mut self, size: PhysicalSize<u32>) {
+        self.window_size = size;
+    }
+
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys.pressed(key)
+    }
+
+    pub fn key_just_pressed(&self, key: KeyCode) -> bool {
+        self.keys.just_pressed(key)
+    }
+
+    pub fn key_just_released(&self, key: KeyCode) -> bool {
+        self.keys.just_released(key)
+    }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.pressed(button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_pressed(button)
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_released(button)
+    }
+
+    /// Cursor in physical pixels from the top-left corner
+    pub fn cursor_position(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor
+    }
+
+    /// `false` after the window lost the keyboard focus, until it gets it back
+    pub fn is_focused(&self) -> bool {
+        !self.unfocused
+    }
+
+    pub fn window_size(&self) -> PhysicalSize<u32> {
+        self.window_size
+    }
+
+    /// Converts a physical position to `0.0..=1.0` from the top-left corner
+    pub fn normalize_position(&self, position: PhysicalPosition<f64>) -> Option<(f32, f32)> {
+        if self.window_size.width == 0 || self.window_size.height == 0 {
+            return None;
+        }
+        Some((
+            (position.x / self.window_size.width as f64) as f32,
+            (position.y / self.window_size.height as f64) as f32,
+        ))
+    }
+
+    /// Cursor in `0.0..=1.0` from the top-left corner
+    pub fn cursor_normalized(&self) -> Option<(f32, f32)> {
+        self.normalize_position(self.cursor?)
+    }
+
+    /// Cursor in normalized device coordinates, `-1.0..=1.0` with y up
+    pub fn cursor_ndc(&self) -> Option<(f32, f32)> {
+        let (x, y) = self.cursor_normalized()?;
+        Some((x * 2.0 - 1.0, 1.0 - y * 2.0))
+    }
+
+    /// Cursor movement this frame in physical pixels
+    pub fn cursor_delta(&self) -> (f64, f64) {
+        self.cursor_delta
+    }
+
+    /// Raw mouse movement this frame, unaffected by pointer lock
+    pub fn mouse_motion(&self) -> (f64, f64) {
+        self.mouse_motion
+    }
+
+    /// Wheel movement this frame in lines, from mice with notched wheels
+    pub fn scroll_lines(&self) -> (f32, f32) {
+        self.scroll_lines
+    }
+
+    /// Wheel movement this frame in pixels, from touchpads
+    pub fn scroll_pixels(&self) -> (f64, f64) {
+        self.scroll_pixels
+    }
+
+    pub fn touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.touches.values()
+    }
+
+    pub fn touch(&self, id: u64) -> Option<&TouchPoint> {
+        self.touches.get(&id)
+    }
+
+    pub fn just_started_touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.just_started.iter().filter_map(move |id| self.touches.get(id))
+    }
+
+    pub fn just_ended_touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.just_ended.iter()
+    }
+}
@@ -1,8 +1,30 @@
 #![allow(warnings)]
 
 mod utils;
+
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use pollster::block_on;
+
 mod graphics;
 pub use graphics::*;
 
+mod app;
+pub use app::*;
+
+mod time;
+pub use time::*;
+
+mod input;
+pub use input::*;
+
+mod action;
+pub use action::*;
+
+pub mod math;
+pub use math::*;
+
+pub use winit::keyboard::KeyCode;
+
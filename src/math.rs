@@ -0,0 +1,34 @@
+//! Vectors, matrices and quaternions, re-exported from `glam`.
+//!
+//! One convention everywhere: matrices are column-major and multiply column
+//! vectors, so a vertex goes through `proj * view * model * position` both in
+//! Rust and in WGSL. Clip space depth is `0.0..=1.0` and the world is right
+//! handed with `+Y` up and the camera looking down `-Z`.
+
+pub use glam;
+pub use glam::{vec2, vec3, vec4, EulerRot, Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
+
+/// Perspective projection, `fov_y` in radians
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    Mat4::perspective_rh(fov_y, aspect, near, far)
+}
+
+/// Orthographic projection of a box in view space
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+    Mat4::orthographic_rh(left, right, bottom, top, near, far)
+}
+
+/// View matrix of a camera at `eye` looking at `target`
+pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+    Mat4::look_at_rh(eye, target, up)
+}
+
+/// Columns of the matrix, the layout of a WGSL `mat4x4<f32>` uniform
+pub fn to_gpu(matrix: Mat4) -> [[f32; 4]; 4] {
+    matrix.to_cols_array_2d()
+}
+
+/// Inverse of [`to_gpu`]
+pub fn from_gpu(columns: [[f32; 4]; 4]) -> Mat4 {
+    Mat4::from_cols_array_2d(&columns)
+}
@@ -0,0 +1,12 @@
+// Fullscreen triangle on the far plane, the color comes from the blend
+// constant so a camera can clear only its viewport, color and depth
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4f(uv * 2.0 - 1.0, 1.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4f {
+    return vec4f(1.0);
+}
\ No newline at end of file
@@ -3,11 +3,21 @@ struct VSOut {
     @location(0) color: vec3f,
 };
 
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var<uniform> model: mat4x4<f32>;
+
 @vertex
 fn vs_main(@location(0) inPos: vec3f,
            @location(1) inColor: vec3f) -> VSOut {
     var vsOut: VSOut;
-    vsOut.Position = vec4f(inPos, 1.0);
+    vsOut.Position = camera.view_proj * model * vec4f(inPos, 1.0);
     vsOut.color = inColor;
 
     return vsOut;
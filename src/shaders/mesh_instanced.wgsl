@@ -0,0 +1,38 @@
+struct VSOut {
+    @builtin(position) Position: vec4f,
+    @location(0) color: vec4f,
+};
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// One model matrix and color per instance, see `InstanceRaw` in types.rs
+struct InstanceIn {
+    @location(4) model_0: vec4f,
+    @location(5) model_1: vec4f,
+    @location(6) model_2: vec4f,
+    @location(7) model_3: vec4f,
+    @location(8) color: vec4f,
+};
+
+@vertex
+fn vs_main(@location(0) inPos: vec3f,
+           @location(1) inColor: vec3f,
+           instance: InstanceIn) -> VSOut {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+
+    var vsOut: VSOut;
+    vsOut.Position = camera.view_proj * model * vec4f(inPos, 1.0);
+    vsOut.color = vec4f(inColor, 1.0) * instance.color;
+
+    return vsOut;
+}
+
+@fragment
+fn fs_main(@location(0) inColor: vec4f) -> @location(0) vec4f {
+    return inColor;
+}
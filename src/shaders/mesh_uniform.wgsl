@@ -1,26 +1,31 @@
-struct VSOut {
-    @builtin(position) Position: vec4f,
-    @location(0) color: vec3f,
-};
-
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> a: CameraUniform;
-
-@vertex
-fn vs_main(@location(0) inPos: vec3f,
-           @location(1) inColor: vec3f) -> VSOut {
-    var vsOut: VSOut;
-    vsOut.Position = vec4f(inPos, 1.0) * a.view_proj;
-    vsOut.color = inColor;
-
-    return vsOut;
-}
-
-@fragment
-fn fs_main(@location(0) inColor: vec3f) -> @location(0) vec4f {
-    return vec4f(inColor, 1);
+struct VSOut {
+    @builtin(position) Position: vec4f,
+    @location(0) color: vec3f,
+};
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var<uniform> model: mat4x4<f32>;
+
+// Uniforms added with `add_uniform` are bound at group 2, in order
+
+@vertex
+fn vs_main(@location(0) inPos: vec3f,
+           @location(1) inColor: vec3f) -> VSOut {
+    var vsOut: VSOut;
+    vsOut.Position = camera.view_proj * model * vec4f(inPos, 1.0);
+    vsOut.color = inColor;
+
+    return vsOut;
+}
+
+@fragment
+fn fs_main(@location(0) inColor: vec3f) -> @location(0) vec4f {
+    return vec4f(inColor, 1);
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+/// Seconds since an arbitrary fixed point, `performance.now()` on the web.
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    web_sys::window()
+        .and_then(|w| w.performance())
+        .expect("Error get performance")
+        .now() / 1000.0
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
+
+/// Frame timing, updated once per frame by the [`App`](crate::App) runner.
+#[derive(Debug, Clone)]
+pub struct Time {
+    last:           Option<f64>,
+    delta:          f64,
+    elapsed:        f64,
+    frame_count:    u64,
+    fps:            f64,
+    /// Weight of the newest frame in the smoothed fps, `0.0..=1.0`
+    pub fps_smoothing: f64,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Time {
+
+    pub fn new() -> Self {
+        Self {
+            last:           None,
+            delta:          0.0,
+            elapsed:        0.0,
+            frame_count:    0,
+            fps:            0.0,
+            fps_smoothing:  0.1,
+        }
+    }
+
+    /// Starts a new frame. The first frame has a zero delta.
+    pub fn update(&mut self) {
+        let now = now();
+        self.update_with_delta(self.last.map_or(0.0, |last| now - last));
+        self.last = Some(now);
+    }
+
+    /// Advances the clock by a known delta, for tests and replays.
+    pub fn update_with_delta(&mut self, delta: f64) {
+        self.delta = delta.max(0.0);
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+
+        if self.delta > 0.0 {
+            let fps = 1.0 / self.delta;
+            self.fps = if self.fps == 0.0 { fps } else { self.fps + (fps - self.fps) * self.fps_smoothing };
+        }
+    }
+
+    /// Seconds since the previous frame
+    pub fn delta(&self) -> f32 {
+        self.delta as f32
+    }
+
+    pub fn delta_f64(&self) -> f64 {
+        self.delta
+    }
+
+    /// Seconds since the first frame
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Exponentially smoothed frames per second
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+}
+
+/// Accumulates frame time and hands out whole simulation steps of a fixed
+/// length, independently of the render rate.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    /// Length of one step in seconds
+    pub step:       f64,
+    /// Upper bound of steps per frame; time beyond it is dropped so a long
+    /// stall does not snowball into ever longer frames
+    pub max_steps:  u32,
+    accumulator:    f64,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::from_hz(60.0)
+    }
+}
+
+impl FixedTimestep {
+
+    pub fn new(step: f64) -> Self {
+        Self {
+            step,
+            max_steps: 5,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn from_hz(hz: f64) -> Self {
+        Self::new(1.0 / hz)
+    }
+
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Adds a frame delta and returns how many steps should run this frame.
+    pub fn advance(&mut self, delta: f64) -> u32 {
+        self.accumulator += delta;
+
+        let steps = (self.accumulator / self.step).floor() as u32;
+        if steps > self.max_steps {
+            self.accumulator = 0.0;
+            return self.max_steps;
+        }
+
+        self.accumulator -= steps as f64 * self.step;
+        steps
+    }
+
+    /// Step length as `f32`, handy as the `dt` of a simulation step
+    pub fn delta(&self) -> f32 {
+        self.step as f32
+    }
+
+    /// How far the accumulator is into the next step, `0.0..1.0`, for
+    /// interpolating rendered state between simulation steps.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step) as f32
+    }
+}
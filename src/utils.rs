@@ -1,6 +1,6 @@
 #![allow(warnings)]
 
 pub fn set_panic_hook() {
-    #[cfg(feature = "console_error_panic_hook")]
+    #[cfg(all(target_arch = "wasm32", feature = "console_error_panic_hook"))]
     console_error_panic_hook::set_once();
 }